@@ -0,0 +1,627 @@
+//!
+//! A minimal headless compositor implementing the WLCS integration, built on smithay
+//!
+//! Build it with `cargo build --example headless` and pass the resulting
+//! `target/debug/examples/libheadless.so` to the `wlcs` test runner.
+//!
+//! The compositor runs on its own thread, without rendering. It implements `wl_compositor`, `wl_shm`,
+//! `xdg_wm_base` and a `wl_seat` with a pointer and touch capability, which is what the WLCS pointer and touch
+//! tests on toplevel windows need: windows are placed where WLCS asks, and the events of the devices WLCS
+//! creates reach the surface under them. Popups, keyboards and other protocols are left out.
+//!
+//! Smithay requires Rust 1.80.1, so this example does not build with the minimum Rust version of the crate.
+//!
+
+use std::{
+    collections::HashMap,
+    io,
+    os::{
+        fd::{OwnedFd, RawFd},
+        unix::net::UnixStream,
+    },
+    sync::{mpsc, Arc},
+    thread::JoinHandle,
+    time::Instant,
+};
+
+use smithay::{
+    backend::renderer::utils::on_commit_buffer_handler,
+    delegate_compositor, delegate_output, delegate_seat, delegate_shm, delegate_xdg_shell,
+    desktop::{Space, Window, WindowSurfaceType},
+    input::{
+        pointer::{ButtonEvent, MotionEvent},
+        touch::{DownEvent, MotionEvent as TouchMotionEvent, UpEvent},
+        Seat, SeatHandler, SeatState,
+    },
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    reexports::{
+        calloop::{
+            channel::{self, Channel},
+            generic::Generic,
+            ping::{make_ping, PingSource},
+            EventLoop, Interest, LoopSignal, Mode as TriggerMode, PostAction,
+        },
+        wayland_server::{
+            backend::{ClientData, ClientId, DisconnectReason},
+            protocol::{wl_buffer::WlBuffer, wl_seat::WlSeat, wl_surface::WlSurface},
+            Client, Display, DisplayHandle,
+        },
+    },
+    utils::{Logical, Point, Serial, SERIAL_COUNTER},
+    wayland::{
+        buffer::BufferHandler,
+        compositor::{
+            get_parent, is_sync_subsurface, with_states, CompositorClientState, CompositorHandler,
+            CompositorState, SurfaceAttributes,
+        },
+        output::OutputHandler,
+        shell::xdg::{
+            PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState,
+            XdgToplevelSurfaceData,
+        },
+        shm::{ShmHandler, ShmState},
+    },
+};
+use wayland_sys::client::wl_display;
+use wlcs::{
+    extension_list,
+    ffi_display_server_api::WlcsIntegrationDescriptor,
+    ffi_wrappers::spawn_server_thread,
+    fixed::Fixed,
+    seat::{SharedSeat, WeakSeat},
+    socket, wlcs_server_integration, Pointer, SurfaceProxy, Touch, Wlcs, WlcsError,
+};
+
+static DESCRIPTOR: WlcsIntegrationDescriptor = WlcsIntegrationDescriptor::new(extension_list!(
+    ("wl_compositor", 5),
+    ("wl_subcompositor", 1),
+    ("wl_shm", 1),
+    ("wl_seat", 7),
+    ("wl_output", 4),
+    ("xdg_wm_base", 6),
+));
+
+/// Input events fed from the devices into the server thread.
+#[derive(Debug)]
+enum Input {
+    PointerMotion { x: Fixed, y: Fixed },
+    PointerMotionRelative { dx: Fixed, dy: Fixed },
+    PointerButton { button: i32, pressed: bool },
+    TouchDown { x: Fixed, y: Fixed },
+    TouchMotion { x: Fixed, y: Fixed },
+    TouchUp,
+}
+
+/// Commands sent from WLCS to the server thread.
+enum Command {
+    /// A client connecting through `stream`, whose other end WLCS connects through `client_fd`.
+    NewClient {
+        stream: UnixStream,
+        client_fd: RawFd,
+    },
+    /// Move the window of surface `surface_id` of the client connected through `client_fd`, replying once done.
+    PositionWindow {
+        client_fd: RawFd,
+        surface_id: u32,
+        location: Point<i32, Logical>,
+        done: mpsc::Sender<()>,
+    },
+}
+
+struct Headless {
+    seat: SharedSeat<Input>,
+    commands: Option<channel::Sender<Command>>,
+    signal: Option<LoopSignal>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Wlcs for Headless {
+    type Pointer = HeadlessPointer;
+    type Touch = HeadlessTouch;
+
+    fn new() -> Result<Self, WlcsError> {
+        Ok(Headless {
+            seat: SharedSeat::new(),
+            commands: None,
+            signal: None,
+            thread: None,
+        })
+    }
+
+    fn start(&mut self) {
+        let (commands, command_source) = channel::channel();
+        let (ping, ping_source) = make_ping().expect("failed to create the input waker");
+        self.seat.set_waker(move || ping.ping());
+        let seat = self.seat.clone();
+        let (signal, started) = mpsc::sync_channel(1);
+        let thread = spawn_server_thread("headless-compositor", move || {
+            run(command_source, ping_source, seat, signal)
+        })
+        .expect("failed to spawn the compositor thread");
+        // WLCS connects clients right after this returns, so wait for the globals to exist.
+        self.signal = Some(
+            started
+                .recv()
+                .expect("the compositor thread failed to start"),
+        );
+        self.commands = Some(commands);
+        self.thread = Some(thread);
+    }
+
+    fn stop(&mut self) -> Result<(), WlcsError> {
+        self.commands = None;
+        if let Some(signal) = self.signal.take() {
+            signal.stop();
+            signal.wakeup();
+        }
+        if let Some(thread) = self.thread.take() {
+            thread
+                .join()
+                .map_err(|_| "the compositor thread panicked")?;
+        }
+        Ok(())
+    }
+
+    fn create_client_socket(&self) -> io::Result<OwnedFd> {
+        let commands = self
+            .commands
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "server not started"))?;
+        let (server, client) = socket::create_socketpair()?;
+        let client_fd = std::os::fd::AsRawFd::as_raw_fd(&client);
+        commands
+            .send(Command::NewClient {
+                stream: server.into(),
+                client_fd,
+            })
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "server thread exited"))?;
+        Ok(client)
+    }
+
+    fn position_window_absolute(
+        &self,
+        display: *mut wl_display,
+        surface: SurfaceProxy<'_>,
+        x: i32,
+        y: i32,
+    ) {
+        let Some(commands) = &self.commands else {
+            return;
+        };
+        let (done, positioned) = mpsc::channel();
+        let command = Command::PositionWindow {
+            // SAFETY: WLCS passes the live display of the client owning the surface.
+            client_fd: unsafe { socket::client_display_fd(display) },
+            surface_id: surface.protocol_id(),
+            location: (x, y).into(),
+            done,
+        };
+        // WLCS checks the position right after this returns, so wait for the server thread to apply it.
+        if commands.send(command).is_ok() {
+            let _ = positioned.recv();
+        }
+    }
+
+    fn create_pointer(&mut self) -> Option<Self::Pointer> {
+        Some(HeadlessPointer(self.seat.downgrade()))
+    }
+
+    fn create_touch(&mut self) -> Option<Self::Touch> {
+        Some(HeadlessTouch(self.seat.downgrade()))
+    }
+
+    fn get_descriptor(&self) -> &WlcsIntegrationDescriptor {
+        &DESCRIPTOR
+    }
+}
+
+struct HeadlessPointer(WeakSeat<Input>);
+
+impl Pointer for HeadlessPointer {
+    fn move_absolute(&mut self, x: Fixed, y: Fixed) {
+        self.0.push(Input::PointerMotion { x, y });
+    }
+
+    fn move_relative(&mut self, dx: Fixed, dy: Fixed) {
+        self.0.push(Input::PointerMotionRelative { dx, dy });
+    }
+
+    fn button_up(&mut self, button: i32) {
+        self.0.push(Input::PointerButton {
+            button,
+            pressed: false,
+        });
+    }
+
+    fn button_down(&mut self, button: i32) {
+        self.0.push(Input::PointerButton {
+            button,
+            pressed: true,
+        });
+    }
+}
+
+struct HeadlessTouch(WeakSeat<Input>);
+
+impl Touch for HeadlessTouch {
+    fn touch_down(&mut self, x: Fixed, y: Fixed) {
+        self.0.push(Input::TouchDown { x, y });
+    }
+
+    fn touch_move(&mut self, x: Fixed, y: Fixed) {
+        self.0.push(Input::TouchMotion { x, y });
+    }
+
+    fn touch_up(&mut self) {
+        self.0.push(Input::TouchUp);
+    }
+}
+
+wlcs_server_integration!(Headless);
+
+/// State of the compositor thread.
+struct State {
+    handle: DisplayHandle,
+    start_time: Instant,
+    compositor_state: CompositorState,
+    xdg_shell_state: XdgShellState,
+    shm_state: ShmState,
+    seat_state: SeatState<State>,
+    seat: Seat<State>,
+    space: Space<Window>,
+    /// The clients, by the fd of the socket WLCS connects them through.
+    clients: HashMap<RawFd, Client>,
+}
+
+impl State {
+    fn new(handle: DisplayHandle) -> Self {
+        let compositor_state = CompositorState::new::<State>(&handle);
+        let xdg_shell_state = XdgShellState::new::<State>(&handle);
+        let shm_state = ShmState::new::<State>(&handle, Vec::new());
+        let mut seat_state = SeatState::new();
+        let mut seat = seat_state.new_wl_seat(&handle, "seat0");
+        seat.add_pointer();
+        seat.add_touch();
+
+        let output = Output::new(
+            "headless".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "wlcs-rs".into(),
+                model: "headless".into(),
+            },
+        );
+        let mode = Mode {
+            size: (1920, 1080).into(),
+            refresh: 60_000,
+        };
+        let _global = output.create_global::<State>(&handle);
+        output.change_current_state(Some(mode), None, None, Some((0, 0).into()));
+        output.set_preferred(mode);
+        let mut space = Space::default();
+        space.map_output(&output, (0, 0));
+
+        State {
+            handle,
+            start_time: Instant::now(),
+            compositor_state,
+            xdg_shell_state,
+            shm_state,
+            seat_state,
+            seat,
+            space,
+            clients: HashMap::new(),
+        }
+    }
+
+    fn time(&self) -> u32 {
+        self.start_time.elapsed().as_millis() as u32
+    }
+
+    fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::NewClient { stream, client_fd } => {
+                match self
+                    .handle
+                    .insert_client(stream, Arc::new(ClientState::default()))
+                {
+                    Ok(client) => {
+                        self.clients.insert(client_fd, client);
+                    }
+                    Err(err) => eprintln!("failed to insert client: {}", err),
+                }
+            }
+            Command::PositionWindow {
+                client_fd,
+                surface_id,
+                location,
+                done,
+            } => {
+                let surface = self.clients.get(&client_fd).and_then(|client| {
+                    client
+                        .object_from_protocol_id::<WlSurface>(&self.handle, surface_id)
+                        .ok()
+                });
+                let window = surface.and_then(|surface| {
+                    self.space
+                        .elements()
+                        .find(|window| {
+                            window.toplevel().map(ToplevelSurface::wl_surface) == Some(&surface)
+                        })
+                        .cloned()
+                });
+                match window {
+                    Some(window) => self.space.map_element(window, location, false),
+                    None => eprintln!("no window for surface {} to position", surface_id),
+                }
+                let _ = done.send(());
+            }
+        }
+    }
+
+    /// The surface under `location`, with its position in the global compositor space.
+    fn surface_under(
+        &self,
+        location: Point<f64, Logical>,
+    ) -> Option<(WlSurface, Point<f64, Logical>)> {
+        let (window, window_location) = self.space.element_under(location)?;
+        window
+            .surface_under(location - window_location.to_f64(), WindowSurfaceType::ALL)
+            .map(|(surface, surface_location)| {
+                (surface, (surface_location + window_location).to_f64())
+            })
+    }
+
+    fn handle_input(&mut self, input: Input) {
+        let serial = SERIAL_COUNTER.next_serial();
+        let time = self.time();
+        match input {
+            Input::PointerMotion { .. } | Input::PointerMotionRelative { .. } => {
+                let pointer = self.seat.get_pointer().unwrap();
+                let location = match input {
+                    Input::PointerMotion { x, y } => (x.to_f64(), y.to_f64()).into(),
+                    Input::PointerMotionRelative { dx, dy } => {
+                        pointer.current_location() + Point::from((dx.to_f64(), dy.to_f64()))
+                    }
+                    _ => unreachable!(),
+                };
+                let under = self.surface_under(location);
+                pointer.motion(
+                    self,
+                    under,
+                    &MotionEvent {
+                        location,
+                        serial,
+                        time,
+                    },
+                );
+                pointer.frame(self);
+            }
+            Input::PointerButton { button, pressed } => {
+                let pointer = self.seat.get_pointer().unwrap();
+                pointer.button(
+                    self,
+                    &ButtonEvent {
+                        serial,
+                        time,
+                        button: button as u32,
+                        state: if pressed {
+                            smithay::backend::input::ButtonState::Pressed
+                        } else {
+                            smithay::backend::input::ButtonState::Released
+                        },
+                    },
+                );
+                pointer.frame(self);
+            }
+            Input::TouchDown { x, y } => {
+                let touch = self.seat.get_touch().unwrap();
+                let location = (x.to_f64(), y.to_f64()).into();
+                let under = self.surface_under(location);
+                touch.down(
+                    self,
+                    under,
+                    &DownEvent {
+                        slot: Some(0).into(),
+                        location,
+                        serial,
+                        time,
+                    },
+                );
+                touch.frame(self);
+            }
+            Input::TouchMotion { x, y } => {
+                let touch = self.seat.get_touch().unwrap();
+                let location = (x.to_f64(), y.to_f64()).into();
+                let under = self.surface_under(location);
+                touch.motion(
+                    self,
+                    under,
+                    &TouchMotionEvent {
+                        slot: Some(0).into(),
+                        location,
+                        time,
+                    },
+                );
+                touch.frame(self);
+            }
+            Input::TouchUp => {
+                let touch = self.seat.get_touch().unwrap();
+                touch.up(
+                    self,
+                    &UpEvent {
+                        slot: Some(0).into(),
+                        serial,
+                        time,
+                    },
+                );
+                touch.frame(self);
+            }
+        }
+    }
+}
+
+fn run(
+    commands: Channel<Command>,
+    input: PingSource,
+    seat: SharedSeat<Input>,
+    started: mpsc::SyncSender<LoopSignal>,
+) {
+    let mut event_loop = EventLoop::<State>::try_new().expect("failed to create the event loop");
+    let display = Display::<State>::new().expect("failed to create the display");
+    let mut state = State::new(display.handle());
+    let handle = event_loop.handle();
+
+    handle
+        .insert_source(
+            Generic::new(display, Interest::READ, TriggerMode::Level),
+            |_, display, state| {
+                // SAFETY: the display is not dropped while the event loop runs.
+                unsafe { display.get_mut() }
+                    .dispatch_clients(state)
+                    .map(|_| PostAction::Continue)
+            },
+        )
+        .expect("failed to watch the display");
+    handle
+        .insert_source(commands, |event, _, state| {
+            if let channel::Event::Msg(command) = event {
+                state.handle_command(command);
+            }
+        })
+        .expect("failed to watch commands");
+    handle
+        .insert_source(input, move |_, _, state| {
+            for input in seat.drain() {
+                state.handle_input(input);
+            }
+        })
+        .expect("failed to watch input");
+
+    let _ = started.send(event_loop.get_signal());
+    event_loop
+        .run(None, &mut state, |state| {
+            state.space.refresh();
+            let _ = state.handle.flush_clients();
+        })
+        .expect("failed to run the event loop");
+}
+
+#[derive(Default)]
+struct ClientState {
+    compositor_state: CompositorClientState,
+}
+
+impl ClientData for ClientState {
+    fn initialized(&self, _client_id: ClientId) {}
+    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+}
+
+impl CompositorHandler for State {
+    fn compositor_state(&mut self) -> &mut CompositorState {
+        &mut self.compositor_state
+    }
+
+    fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState {
+        &client.get_data::<ClientState>().unwrap().compositor_state
+    }
+
+    fn commit(&mut self, surface: &WlSurface) {
+        on_commit_buffer_handler::<Self>(surface);
+        if !is_sync_subsurface(surface) {
+            let mut root = surface.clone();
+            while let Some(parent) = get_parent(&root) {
+                root = parent;
+            }
+            if let Some(window) = self
+                .space
+                .elements()
+                .find(|window| window.toplevel().map(ToplevelSurface::wl_surface) == Some(&root))
+            {
+                window.on_commit();
+            }
+        }
+
+        // Reply to the initial commit of a toplevel with its first configure.
+        if let Some(toplevel) = self
+            .space
+            .elements()
+            .filter_map(Window::toplevel)
+            .find(|toplevel| toplevel.wl_surface() == surface)
+        {
+            let initial_configure_sent = with_states(surface, |states| {
+                states
+                    .data_map
+                    .get::<XdgToplevelSurfaceData>()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .initial_configure_sent
+            });
+            if !initial_configure_sent {
+                toplevel.send_configure();
+            }
+        }
+
+        // Nothing is rendered, so frames are done right away.
+        let time = self.time();
+        with_states(surface, |states| {
+            let mut attributes = states.cached_state.get::<SurfaceAttributes>();
+            for callback in attributes.current().frame_callbacks.drain(..) {
+                callback.done(time);
+            }
+        });
+    }
+}
+
+impl BufferHandler for State {
+    fn buffer_destroyed(&mut self, _buffer: &WlBuffer) {}
+}
+
+impl ShmHandler for State {
+    fn shm_state(&self) -> &ShmState {
+        &self.shm_state
+    }
+}
+
+impl XdgShellHandler for State {
+    fn xdg_shell_state(&mut self) -> &mut XdgShellState {
+        &mut self.xdg_shell_state
+    }
+
+    fn new_toplevel(&mut self, surface: ToplevelSurface) {
+        self.space
+            .map_element(Window::new_wayland_window(surface), (0, 0), false);
+    }
+
+    fn new_popup(&mut self, _surface: PopupSurface, _positioner: PositionerState) {}
+
+    fn grab(&mut self, _surface: PopupSurface, _seat: WlSeat, _serial: Serial) {}
+
+    fn reposition_request(
+        &mut self,
+        _surface: PopupSurface,
+        _positioner: PositionerState,
+        _token: u32,
+    ) {
+    }
+}
+
+impl SeatHandler for State {
+    type KeyboardFocus = WlSurface;
+    type PointerFocus = WlSurface;
+    type TouchFocus = WlSurface;
+
+    fn seat_state(&mut self) -> &mut SeatState<State> {
+        &mut self.seat_state
+    }
+}
+
+impl OutputHandler for State {}
+
+delegate_compositor!(State);
+delegate_shm!(State);
+delegate_xdg_shell!(State);
+delegate_seat!(State);
+delegate_output!(State);
@@ -0,0 +1,60 @@
+//!
+//! Clocks for event timestamps
+//!
+//! Compositors stamp input events with the time they happened, in milliseconds for `wl_pointer` and `wl_touch`
+//! and in microseconds for `zwp_relative_pointer_v1`, on the monotonic clock. Tests of timeouts, e.g. of key
+//! repeat or double-click intervals, need to control that time instead of sleeping, which [`ClockSource`] allows.
+//!
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// The clock event timestamps derive from, see [`Wlcs::set_clock`](crate::Wlcs::set_clock).
+#[derive(Debug, Clone, Default)]
+pub enum ClockSource {
+    /// The monotonic clock of the system.
+    #[default]
+    System,
+    /// A time in microseconds, only advanced by the test holding the other end of the `Arc`.
+    Manual(Arc<AtomicU64>),
+}
+
+impl ClockSource {
+    /// A manual clock starting at 0, along with the handle advancing it.
+    pub fn manual() -> (Self, Arc<AtomicU64>) {
+        let time = Arc::new(AtomicU64::new(0));
+        (ClockSource::Manual(time.clone()), time)
+    }
+
+    /// The current time in microseconds.
+    pub fn now_usec(&self) -> u64 {
+        match self {
+            ClockSource::System => crate::sys::monotonic_usec(),
+            ClockSource::Manual(time) => time.load(Ordering::Acquire),
+        }
+    }
+
+    /// The current time in milliseconds, wrapping around like the 32-bit timestamps of the protocol.
+    pub fn now_msec(&self) -> u32 {
+        (self.now_usec() / 1000) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock() {
+        let (clock, time) = ClockSource::manual();
+        assert_eq!(clock.now_usec(), 0);
+        time.fetch_add(1_500_000, Ordering::Release);
+        assert_eq!(clock.now_usec(), 1_500_000);
+        assert_eq!(clock.now_msec(), 1500);
+
+        let system = ClockSource::System;
+        assert!(system.now_usec() <= system.now_usec());
+    }
+}
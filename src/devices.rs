@@ -0,0 +1,423 @@
+//!
+//! Reusable device implementations
+//!
+//! [`NoopPointer`] and [`NoopTouch`] ignore every event, for integrations which do not test input.
+//!
+//! [`CoalescingPointer`] wraps the pointer of an integration, merging bursts of relative motion into single
+//! events to mimic the rate of real input devices.
+//!
+//! [`TouchAsPointer`] emulates touch with the pointer of an integration, for compositors without touch support.
+//!
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    ffi_pointer_api::BTN_LEFT,
+    fixed::Fixed,
+    geometry::{FixedPoint, Rectangle},
+    Pointer, Touch,
+};
+
+/// A [`Pointer`] ignoring every event.
+///
+/// Integrations which only test windowing can use it as [`Wlcs::Pointer`](crate::Wlcs::Pointer), return `None`
+/// from [`Wlcs::create_pointer`](crate::Wlcs::create_pointer), and return `false` from
+/// [`Wlcs::supports_pointer`](crate::Wlcs::supports_pointer) so WLCS skips the pointer tests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoopPointer;
+
+impl Pointer for NoopPointer {
+    fn move_absolute(&mut self, _x: Fixed, _y: Fixed) {}
+
+    fn move_relative(&mut self, _dx: Fixed, _dy: Fixed) {}
+
+    fn button_up(&mut self, _button: i32) {}
+
+    fn button_down(&mut self, _button: i32) {}
+}
+
+/// A [`Touch`] ignoring every event, see [`NoopPointer`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoopTouch;
+
+impl Touch for NoopTouch {
+    fn touch_down(&mut self, _x: Fixed, _y: Fixed) {}
+
+    fn touch_move(&mut self, _x: Fixed, _y: Fixed) {}
+
+    fn touch_up(&mut self) {}
+}
+
+/// Relative motion accumulated by [`CoalescingPointer`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Motion {
+    dx: Fixed,
+    dy: Fixed,
+    dx_unaccel: Fixed,
+    dy_unaccel: Fixed,
+}
+
+/// A [`Pointer`] forwarding to `P`, coalescing relative motion within an interval set with
+/// [`Pointer::set_coalesce_interval`].
+///
+/// Coalescing is off by default, and every event is forwarded as it comes. Once an interval is set, the first
+/// relative motion is forwarded right away, and the motion received within the interval after it is summed up
+/// and forwarded as a single event by the first relative motion after the interval elapsed. As there is no
+/// timer, pending motion is also forwarded before any other event, keeping the events in order, and by
+/// [`CoalescingPointer::flush`]. Until then, [`Pointer::position`] of the wrapped pointer does not include it.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use wlcs::{
+///     devices::CoalescingPointer,
+///     fixed::Fixed,
+///     testing::{EventLog, FnPointer, RecordedEvent, RecordingPointer},
+///     Pointer,
+/// };
+///
+/// let log = EventLog::new();
+/// let mut pointer = CoalescingPointer::new(RecordingPointer::new(FnPointer::new(), log.clone()));
+/// pointer.set_coalesce_interval(Duration::from_secs(1));
+/// for _ in 0..3 {
+///     pointer.move_relative(Fixed::from_int(1), Fixed::ZERO);
+/// }
+/// pointer.flush();
+///
+/// let relative = |dx| RecordedEvent::PointerMoveRelative {
+///     dx: Fixed::from_int(dx),
+///     dy: Fixed::ZERO,
+///     dx_unaccel: Fixed::from_int(dx),
+///     dy_unaccel: Fixed::ZERO,
+/// };
+/// // The first motion is forwarded right away, the two others once merged.
+/// assert_eq!(log.take(), [relative(1), relative(2)]);
+/// ```
+#[derive(Debug)]
+pub struct CoalescingPointer<P> {
+    inner: P,
+    interval: Duration,
+    pending: Option<Motion>,
+    last_delivery: Option<Instant>,
+}
+
+impl<P> CoalescingPointer<P> {
+    /// Wrap `inner`, with coalescing off.
+    pub fn new(inner: P) -> Self {
+        CoalescingPointer {
+            inner,
+            interval: Duration::ZERO,
+            pending: None,
+            last_delivery: None,
+        }
+    }
+
+    /// The wrapped pointer.
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// The wrapped pointer.
+    pub fn inner_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+
+    /// Unwrap the pointer, dropping any pending motion; see [`CoalescingPointer::flush`].
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    /// The coalescing interval, zero if coalescing is off.
+    pub fn coalesce_interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+impl<P: Pointer> CoalescingPointer<P> {
+    /// Forward the pending motion, if any.
+    pub fn flush(&mut self) {
+        let Some(motion) = self.pending.take() else {
+            return;
+        };
+        self.last_delivery = Some(Instant::now());
+        if motion.dx == motion.dx_unaccel && motion.dy == motion.dy_unaccel {
+            self.inner.move_relative(motion.dx, motion.dy);
+        } else {
+            self.inner.move_relative_with_unaccel(
+                motion.dx,
+                motion.dy,
+                motion.dx_unaccel,
+                motion.dy_unaccel,
+            );
+        }
+    }
+
+    fn coalesce(&mut self, motion: Motion) {
+        let pending = self.pending.get_or_insert_with(Motion::default);
+        pending.dx = pending.dx.saturating_add(motion.dx);
+        pending.dy = pending.dy.saturating_add(motion.dy);
+        pending.dx_unaccel = pending.dx_unaccel.saturating_add(motion.dx_unaccel);
+        pending.dy_unaccel = pending.dy_unaccel.saturating_add(motion.dy_unaccel);
+
+        let elapsed = self
+            .last_delivery
+            .map_or(true, |last| last.elapsed() >= self.interval);
+        if elapsed {
+            self.flush();
+        }
+    }
+}
+
+impl<P: Pointer> Pointer for CoalescingPointer<P> {
+    fn move_absolute(&mut self, x: Fixed, y: Fixed) {
+        self.flush();
+        self.inner.move_absolute(x, y);
+    }
+
+    fn move_relative(&mut self, dx: Fixed, dy: Fixed) {
+        self.move_relative_with_unaccel(dx, dy, dx, dy);
+    }
+
+    fn move_relative_with_unaccel(
+        &mut self,
+        dx: Fixed,
+        dy: Fixed,
+        dx_unaccel: Fixed,
+        dy_unaccel: Fixed,
+    ) {
+        self.coalesce(Motion {
+            dx,
+            dy,
+            dx_unaccel,
+            dy_unaccel,
+        });
+    }
+
+    fn relative_motion_timed(
+        &mut self,
+        utime: u64,
+        dx: Fixed,
+        dy: Fixed,
+        dx_unaccel: Fixed,
+        dy_unaccel: Fixed,
+    ) {
+        // Timed motion is not coalesced, as merging it would lose the timestamps.
+        self.flush();
+        self.inner
+            .relative_motion_timed(utime, dx, dy, dx_unaccel, dy_unaccel);
+    }
+
+    fn move_absolute_f64(&mut self, x: f64, y: f64) {
+        self.flush();
+        self.inner.move_absolute_f64(x, y);
+    }
+
+    fn move_absolute_point(&mut self, point: FixedPoint) {
+        self.flush();
+        self.inner.move_absolute_point(point);
+    }
+
+    fn button_up(&mut self, button: i32) {
+        self.flush();
+        self.inner.button_up(button);
+    }
+
+    fn button_down(&mut self, button: i32) {
+        self.flush();
+        self.inner.button_down(button);
+    }
+
+    fn last_serial(&self) -> Option<u32> {
+        self.inner.last_serial()
+    }
+
+    fn position(&self) -> Option<FixedPoint> {
+        self.inner.position()
+    }
+
+    fn set_bounds(&mut self, region: Rectangle) {
+        self.inner.set_bounds(region)
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        self.inner.bounds()
+    }
+
+    fn set_confine_region(&mut self, region: Option<Rectangle>) {
+        self.flush();
+        self.inner.set_confine_region(region)
+    }
+
+    fn confine_region(&self) -> Option<Rectangle> {
+        self.inner.confine_region()
+    }
+
+    fn move_relative_confined(&mut self, dx: Fixed, dy: Fixed) {
+        if self.confine_region().is_none() {
+            self.move_relative(dx, dy);
+        } else {
+            // Clamping needs the position of the wrapped pointer, which does not include pending motion.
+            self.flush();
+            self.inner.move_relative_confined(dx, dy);
+        }
+    }
+
+    fn axis_source(&mut self, source: u32) {
+        self.flush();
+        self.inner.axis_source(source);
+    }
+
+    fn axis_stop(&mut self, axis: u32) {
+        self.flush();
+        self.inner.axis_stop(axis);
+    }
+
+    fn set_time(&mut self, time_msec: u32) {
+        self.flush();
+        self.inner.set_time(time_msec);
+    }
+
+    fn set_interface_version(&mut self, version: u32) {
+        self.inner.set_interface_version(version);
+    }
+
+    fn set_coalesce_interval(&mut self, interval: Duration) {
+        self.flush();
+        self.interval = interval;
+        self.last_delivery = None;
+    }
+
+    fn remove(&mut self) {
+        self.flush();
+        self.inner.remove();
+    }
+
+    fn destroy(&mut self) {
+        self.flush();
+        self.inner.destroy();
+    }
+}
+
+/// A [`Touch`] emulated with the pointer `P`, for integrations whose compositor has no touch support.
+///
+/// A touch point is translated to the left button held down while the pointer follows it: `touch_down` warps
+/// the pointer to the touch point and presses the button, `touch_move` warps it again, and `touch_up` releases
+/// the button. The emulation is limited to a single touch point, as WLCS drives each touch device, and clients
+/// receive `wl_pointer` events rather than `wl_touch` ones: it only lets basic tests which check the effect of
+/// a touch, e.g. a window being moved, pass. Frames, shapes and orientations are dropped.
+#[derive(Debug)]
+pub struct TouchAsPointer<P> {
+    pointer: P,
+}
+
+impl<P> TouchAsPointer<P> {
+    /// Emulate touch with `pointer`.
+    pub fn new(pointer: P) -> Self {
+        TouchAsPointer { pointer }
+    }
+
+    /// The wrapped pointer.
+    pub fn inner(&self) -> &P {
+        &self.pointer
+    }
+
+    /// The wrapped pointer.
+    pub fn inner_mut(&mut self) -> &mut P {
+        &mut self.pointer
+    }
+
+    /// Unwrap the pointer.
+    pub fn into_inner(self) -> P {
+        self.pointer
+    }
+}
+
+impl<P: Pointer> Touch for TouchAsPointer<P> {
+    fn touch_down(&mut self, x: Fixed, y: Fixed) {
+        self.pointer.move_absolute(x, y);
+        self.pointer.button_down(BTN_LEFT);
+    }
+
+    fn touch_move(&mut self, x: Fixed, y: Fixed) {
+        self.pointer.move_absolute(x, y);
+    }
+
+    fn touch_up(&mut self) {
+        self.pointer.button_up(BTN_LEFT);
+    }
+
+    fn destroy(&mut self) {
+        self.pointer.destroy();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{EventLog, FnPointer, RecordedEvent, RecordingPointer};
+
+    fn relative(dx: i32, dy: i32) -> RecordedEvent {
+        RecordedEvent::PointerMoveRelative {
+            dx: Fixed::from_int(dx),
+            dy: Fixed::from_int(dy),
+            dx_unaccel: Fixed::from_int(dx),
+            dy_unaccel: Fixed::from_int(dy),
+        }
+    }
+
+    #[test]
+    fn touch_as_pointer() {
+        let log = EventLog::new();
+        let mut touch = TouchAsPointer::new(RecordingPointer::new(FnPointer::new(), log.clone()));
+
+        touch.touch_down(Fixed::from_int(1), Fixed::from_int(2));
+        touch.touch_move(Fixed::from_int(3), Fixed::from_int(4));
+        touch.touch_up();
+        assert_eq!(
+            log.take(),
+            [
+                RecordedEvent::PointerMoveAbsolute {
+                    x: Fixed::from_int(1),
+                    y: Fixed::from_int(2)
+                },
+                RecordedEvent::PointerButtonDown { button: BTN_LEFT },
+                RecordedEvent::PointerMoveAbsolute {
+                    x: Fixed::from_int(3),
+                    y: Fixed::from_int(4)
+                },
+                RecordedEvent::PointerButtonUp { button: BTN_LEFT },
+            ]
+        );
+    }
+
+    #[test]
+    fn coalesces_relative_motion() {
+        let log = EventLog::new();
+        let mut pointer =
+            CoalescingPointer::new(RecordingPointer::new(FnPointer::new(), log.clone()));
+
+        pointer.move_relative(Fixed::from_int(1), Fixed::ZERO);
+        pointer.move_relative(Fixed::from_int(1), Fixed::ZERO);
+        assert_eq!(log.take(), [relative(1, 0), relative(1, 0)]);
+
+        pointer.set_coalesce_interval(Duration::from_secs(3600));
+        pointer.move_relative(Fixed::from_int(1), Fixed::from_int(2));
+        pointer.move_relative(Fixed::from_int(3), Fixed::from_int(4));
+        pointer.move_relative(Fixed::from_int(5), Fixed::from_int(6));
+        pointer.button_down(BTN_LEFT);
+        assert_eq!(
+            log.take(),
+            [
+                relative(1, 2),
+                relative(8, 10),
+                RecordedEvent::PointerButtonDown { button: BTN_LEFT },
+            ]
+        );
+
+        pointer.move_relative(Fixed::from_int(1), Fixed::from_int(1));
+        pointer.move_relative(Fixed::from_int(1), Fixed::from_int(1));
+        pointer.remove();
+        assert_eq!(log.take(), [relative(2, 2), RecordedEvent::PointerRemove]);
+    }
+}
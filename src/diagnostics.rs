@@ -0,0 +1,257 @@
+//!
+//! Diagnostics emitted by the FFI wrappers
+//!
+//! Messages go to stdout by default, or through the `log` crate when the `log` feature is enabled. With the
+//! `journald` feature, they are also sent to the systemd journal, where they outlive the terminal of headless
+//! CI runs.
+//!
+
+use std::{any::Any, fmt, sync::RwLock};
+
+/// Formats the message reported for a panic caught at the FFI boundary.
+pub(crate) type PanicFormatter = fn(context: &str, payload: &dyn Any) -> String;
+
+static PANIC_FORMATTER: RwLock<PanicFormatter> = RwLock::new(default_panic_format);
+
+pub(crate) fn set_panic_formatter(formatter: PanicFormatter) {
+    *PANIC_FORMATTER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = formatter;
+}
+
+fn default_panic_format(context: &str, payload: &dyn Any) -> String {
+    match panic_message(payload) {
+        Some(message) => format!("panic in {}: {}", context, message),
+        None => format!(
+            "panic in {} on ptr: {:p} (type {:?})",
+            context,
+            payload as *const _,
+            payload.type_id()
+        ),
+    }
+}
+
+/// The message of a panic payload, which is a `&str` or a `String` for the panics of `panic!` and friends.
+pub(crate) fn panic_message(payload: &dyn Any) -> Option<&str> {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+}
+
+/// Emit an error diagnostic.
+pub(crate) fn error(args: fmt::Arguments<'_>) {
+    #[cfg(feature = "journald")]
+    journal(JOURNAL_PRIORITY_ERR, args);
+    #[cfg(feature = "log")]
+    log::error!("{}", args);
+    #[cfg(not(feature = "log"))]
+    print(args);
+}
+
+/// Emit a warning diagnostic.
+pub(crate) fn warn(args: fmt::Arguments<'_>) {
+    #[cfg(feature = "journald")]
+    journal(JOURNAL_PRIORITY_WARNING, args);
+    #[cfg(feature = "log")]
+    log::warn!("{}", args);
+    #[cfg(not(feature = "log"))]
+    print(args);
+}
+
+/// Emit an informational diagnostic.
+pub(crate) fn info(args: fmt::Arguments<'_>) {
+    #[cfg(feature = "journald")]
+    journal(JOURNAL_PRIORITY_INFO, args);
+    #[cfg(feature = "log")]
+    log::info!("{}", args);
+    #[cfg(not(feature = "log"))]
+    print(args);
+}
+
+/// Print a diagnostic line to stdout and flush it, as WLCS may abort right after a failing call, losing
+/// anything still buffered.
+#[cfg(not(feature = "log"))]
+fn print(args: fmt::Arguments<'_>) {
+    use std::io::Write;
+
+    let mut stdout = std::io::stdout().lock();
+    // There is nowhere left to report failing diagnostics to.
+    let _ = writeln!(stdout, "{}", args);
+    let _ = stdout.flush();
+}
+
+#[cfg(feature = "journald")]
+const JOURNAL_PRIORITY_ERR: u8 = 3;
+#[cfg(feature = "journald")]
+const JOURNAL_PRIORITY_WARNING: u8 = 4;
+#[cfg(feature = "journald")]
+const JOURNAL_PRIORITY_INFO: u8 = 6;
+
+/// Send a diagnostic line to the systemd journal, if journald is running.
+///
+/// This speaks the native journal protocol over its socket, so it needs no libsystemd. Without journald, e.g.
+/// when not running under systemd, diagnostics only go to the regular sink.
+#[cfg(feature = "journald")]
+fn journal(priority: u8, args: fmt::Arguments<'_>) {
+    use std::{os::unix::net::UnixDatagram, sync::OnceLock};
+
+    static JOURNAL: OnceLock<Option<UnixDatagram>> = OnceLock::new();
+    let journal = JOURNAL.get_or_init(|| {
+        let socket = UnixDatagram::unbound().ok()?;
+        socket.connect("/run/systemd/journal/socket").ok()?;
+        Some(socket)
+    });
+    if let Some(journal) = journal {
+        // The diagnostic still reaches the regular sink, so failures are not reported.
+        let _ = journal.send(&journal_entry(priority, &args.to_string()));
+    }
+}
+
+/// Encode a journal entry, with the message in the binary form of the protocol, which allows newlines.
+#[cfg(feature = "journald")]
+fn journal_entry(priority: u8, message: &str) -> Vec<u8> {
+    let mut entry = format!(
+        "PRIORITY={}\nSYSLOG_IDENTIFIER=wlcs-rs\nMESSAGE\n",
+        priority
+    )
+    .into_bytes();
+    entry.extend_from_slice(&(message.len() as u64).to_le_bytes());
+    entry.extend_from_slice(message.as_bytes());
+    entry.push(b'\n');
+    entry
+}
+
+/// Report a panic caught at the FFI boundary.
+///
+/// `context` names the wrapper the panic was caught in. The message is built by the formatter set with
+/// [`set_panic_formatter`].
+pub(crate) fn report_panic(context: &str, err: &(dyn Any + Send)) {
+    let formatter = *PANIC_FORMATTER
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    error(format_args!("{}", formatter(context, err)));
+}
+
+/// Format a call made by WLCS, like `touch_down_ffi(0x5581f0a0, 2560, 1280)`.
+fn format_call(function: &str, args: &[&dyn fmt::Debug]) -> String {
+    use std::fmt::Write as _;
+
+    let mut line = format!("{}(", function);
+    for (i, arg) in args.iter().enumerate() {
+        let separator = if i == 0 { "" } else { ", " };
+        let _ = write!(line, "{}{:?}", separator, arg);
+    }
+    line.push(')');
+    line
+}
+
+/// Whether input events are only logged, if `WLCS_RS_DRY_RUN=1`, in which case the call is logged.
+pub(crate) fn dry_run(function: &str, args: &[&dyn fmt::Debug]) -> bool {
+    static DRY_RUN: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    let dry_run =
+        *DRY_RUN.get_or_init(|| std::env::var_os("WLCS_RS_DRY_RUN").is_some_and(|var| var == "1"));
+    if dry_run {
+        info(format_args!("dry run: {}", format_call(function, args)));
+    }
+    dry_run
+}
+
+/// Append a call made by WLCS, like `touch_down_ffi(0x5581f0a0, 2560, 1280)`, to the file named by the
+/// `WLCS_RS_TRACE` environment variable, if set.
+#[cfg(feature = "trace")]
+pub(crate) fn trace(function: &str, args: &[&dyn fmt::Debug]) {
+    use std::{
+        fs::{File, OpenOptions},
+        io::Write as _,
+        sync::{Mutex, OnceLock},
+    };
+
+    static TRACE: OnceLock<Option<Mutex<File>>> = OnceLock::new();
+    let trace = TRACE.get_or_init(|| {
+        let path = std::env::var_os("WLCS_RS_TRACE")?;
+        match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => Some(Mutex::new(file)),
+            Err(err) => {
+                warn(format_args!(
+                    "failed to open trace file {}: {}",
+                    path.to_string_lossy(),
+                    err
+                ));
+                None
+            }
+        }
+    });
+    let Some(file) = trace else {
+        return;
+    };
+
+    let mut line = format_call(function, args);
+    line.push('\n');
+    // Written at once, so that lines stay whole when several servers trace to the same file.
+    let mut file = file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let _ = file.write_all(line.as_bytes());
+}
+
+/// Warns when an operation takes longer than the configured timeout.
+///
+/// The timeout is read once from the `WLCS_RS_START_TIMEOUT` environment variable (in seconds) and defaults to
+/// 10 seconds. The watchdog is disarmed when dropped.
+#[cfg(feature = "watchdog")]
+pub(crate) struct Watchdog {
+    _disarm: std::sync::mpsc::Sender<()>,
+}
+
+#[cfg(feature = "watchdog")]
+impl Watchdog {
+    pub(crate) fn arm(operation: &'static str) -> Watchdog {
+        use std::{sync::OnceLock, time::Duration};
+
+        static TIMEOUT: OnceLock<Duration> = OnceLock::new();
+        let timeout = *TIMEOUT.get_or_init(|| {
+            let secs = std::env::var("WLCS_RS_START_TIMEOUT")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(10);
+            Duration::from_secs(secs)
+        });
+
+        let (disarm, armed) = std::sync::mpsc::channel::<()>();
+        let spawned = std::thread::Builder::new()
+            .name("wlcs-watchdog".into())
+            .spawn(move || {
+                // Dropping the sender disconnects the channel, anything else means we timed out.
+                if let Err(std::sync::mpsc::RecvTimeoutError::Timeout) = armed.recv_timeout(timeout)
+                {
+                    warn(format_args!(
+                        "{} exceeded {} seconds",
+                        operation,
+                        timeout.as_secs()
+                    ));
+                }
+            });
+        if let Err(err) = spawned {
+            warn(format_args!(
+                "failed to spawn watchdog for {}: {}",
+                operation, err
+            ));
+        }
+
+        Watchdog { _disarm: disarm }
+    }
+}
+
+#[cfg(all(test, feature = "journald"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_entry_encoding() {
+        let entry = journal_entry(JOURNAL_PRIORITY_ERR, "panic in a:\nb");
+        let header = b"PRIORITY=3\nSYSLOG_IDENTIFIER=wlcs-rs\nMESSAGE\n";
+        assert_eq!(&entry[..header.len()], header);
+        let (len, message) = entry[header.len()..].split_at(8);
+        assert_eq!(len, 13u64.to_le_bytes());
+        assert_eq!(message, b"panic in a:\nb\n");
+    }
+}
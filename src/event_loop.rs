@@ -0,0 +1,246 @@
+//!
+//! Safe wrapper around the `wl_event_loop` WLCS passes to
+//! [`Wlcs::start_on_this_thread`](crate::Wlcs::start_on_this_thread)
+//!
+//! WLCS owns the event loop. An integration only borrows it for the duration of the
+//! `start_on_this_thread` call, which is expressed by the lifetime of [`EventLoop`].
+//!
+
+use std::{
+    ffi::{c_int, c_void},
+    io,
+    marker::PhantomData,
+    os::fd::{AsRawFd, BorrowedFd, RawFd},
+    time::Duration,
+};
+
+use wayland_sys::{ffi_dispatch, server::*};
+
+use crate::diagnostics;
+
+/// The fd is readable
+pub const EVENT_READABLE: u32 = 0x01;
+/// The fd is writable
+pub const EVENT_WRITABLE: u32 = 0x02;
+/// The fd was hung up
+pub const EVENT_HANGUP: u32 = 0x04;
+/// An error occurred on the fd
+pub const EVENT_ERROR: u32 = 0x08;
+
+type FdCallback<'a> = Box<dyn FnMut(RawFd, u32) + 'a>;
+type IdleCallback = Box<dyn FnOnce()>;
+
+/// A `wl_event_loop` borrowed from WLCS.
+#[derive(Debug)]
+pub struct EventLoop<'a> {
+    ptr: *mut wl_event_loop,
+    _lifetime: PhantomData<&'a wl_event_loop>,
+}
+
+impl<'a> EventLoop<'a> {
+    /// Wrap a raw `wl_event_loop`.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must refer to a valid `wl_event_loop` which stays alive for `'a` and is only used from the
+    /// current thread.
+    pub unsafe fn from_ptr(ptr: *mut wl_event_loop) -> Self {
+        EventLoop {
+            ptr,
+            _lifetime: PhantomData,
+        }
+    }
+
+    /// The underlying `wl_event_loop` pointer.
+    pub fn as_ptr(&self) -> *mut wl_event_loop {
+        self.ptr
+    }
+
+    /// Dispatch pending events, waiting at most `timeout` for new ones (forever if `None`).
+    pub fn dispatch(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let timeout = timeout.map_or(-1, |timeout| {
+            c_int::try_from(timeout.as_millis()).unwrap_or(c_int::MAX)
+        });
+        let ret = unsafe {
+            ffi_dispatch!(
+                wayland_server_handle(),
+                wl_event_loop_dispatch,
+                self.ptr,
+                timeout
+            )
+        };
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The epoll fd of the event loop, which becomes readable when events are pending.
+    pub fn get_fd(&self) -> BorrowedFd<'a> {
+        let fd = unsafe { ffi_dispatch!(wayland_server_handle(), wl_event_loop_get_fd, self.ptr) };
+        // SAFETY: the fd is owned by the event loop, which outlives 'a.
+        unsafe { BorrowedFd::borrow_raw(fd) }
+    }
+
+    /// Watch `fd` for the events in `mask` (see the `EVENT_*` constants).
+    ///
+    /// `callback` is called with the fd and the triggered events while the loop is dispatched. Panics in the
+    /// callback are caught and reported. The fd is watched until the returned [`EventSource`] is dropped.
+    pub fn add_fd<F>(
+        &self,
+        fd: BorrowedFd<'a>,
+        mask: u32,
+        callback: F,
+    ) -> io::Result<EventSource<'a>>
+    where
+        F: FnMut(RawFd, u32) + 'a,
+    {
+        let callback: *mut FdCallback<'a> = Box::into_raw(Box::new(Box::new(callback)));
+        let source = unsafe {
+            ffi_dispatch!(
+                wayland_server_handle(),
+                wl_event_loop_add_fd,
+                self.ptr,
+                fd.as_raw_fd(),
+                mask,
+                fd_callback,
+                callback as *mut c_void
+            )
+        };
+        if source.is_null() {
+            let err = io::Error::last_os_error();
+            // SAFETY: the event loop did not take the callback.
+            drop(unsafe { Box::from_raw(callback) });
+            return Err(err);
+        }
+
+        Ok(EventSource {
+            ptr: source,
+            callback,
+        })
+    }
+
+    /// Run `f` once, the next time the loop is dispatched, before waiting for new events.
+    ///
+    /// This defers work onto the thread of the event loop, e.g. from within another callback. As `EventLoop`
+    /// cannot be sent to other threads, neither can this be called from them: those can push their work to a
+    /// [`SharedSeat`](crate::seat::SharedSeat) whose waker makes an fd watched with [`EventLoop::add_fd`]
+    /// readable. Panics in `f` are caught and reported.
+    ///
+    /// `f` may outlive the borrow of the loop, as WLCS may dispatch it after the end of `start_on_this_thread`,
+    /// hence the `'static` bound. If the loop is destroyed before `f` runs, `f` is leaked without being called or
+    /// dropped.
+    pub fn post<F>(&self, f: F) -> io::Result<()>
+    where
+        F: FnOnce() + 'static,
+    {
+        let callback: *mut IdleCallback = Box::into_raw(Box::new(Box::new(f)));
+        let source = unsafe {
+            ffi_dispatch!(
+                wayland_server_handle(),
+                wl_event_loop_add_idle,
+                self.ptr,
+                idle_callback,
+                callback as *mut c_void
+            )
+        };
+        if source.is_null() {
+            let err = io::Error::last_os_error();
+            // SAFETY: the event loop did not take the callback.
+            drop(unsafe { Box::from_raw(callback) });
+            return Err(err);
+        }
+        // The event loop removes idle sources once they ran.
+        Ok(())
+    }
+}
+
+/// An event source registered on an [`EventLoop`], removed when dropped.
+#[derive(Debug)]
+pub struct EventSource<'a> {
+    ptr: *mut wl_event_source,
+    callback: *mut FdCallback<'a>,
+}
+
+impl Drop for EventSource<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(wayland_server_handle(), wl_event_source_remove, self.ptr);
+            // SAFETY: the source was removed, so the event loop no longer uses the callback.
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+unsafe extern "C" fn fd_callback(fd: c_int, mask: u32, data: *mut c_void) -> c_int {
+    // SAFETY: data is the callback registered in EventLoop::add_fd, which lives until the source is removed.
+    let callback = unsafe { &mut *(data as *mut FdCallback<'_>) };
+    if let Err(err) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| callback(fd, mask)))
+    {
+        diagnostics::report_panic("event_loop::fd_callback", &*err);
+    }
+    0
+}
+
+unsafe extern "C" fn idle_callback(data: *mut c_void) {
+    // SAFETY: data is the callback registered in EventLoop::post, which is called once.
+    let callback = unsafe { Box::from_raw(data as *mut IdleCallback) };
+    if let Err(err) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(callback)) {
+        diagnostics::report_panic("event_loop::idle_callback", &*err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, io::Write, os::fd::AsFd, os::unix::net::UnixStream, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn add_fd_dispatch() {
+        let ptr = unsafe { ffi_dispatch!(wayland_server_handle(), wl_event_loop_create) };
+        assert!(!ptr.is_null());
+
+        {
+            let event_loop = unsafe { EventLoop::from_ptr(ptr) };
+            let (mut tx, rx) = UnixStream::pair().unwrap();
+            let readable = Cell::new(false);
+            let _source = event_loop
+                .add_fd(rx.as_fd(), EVENT_READABLE, |_, mask| {
+                    readable.set(mask & EVENT_READABLE != 0)
+                })
+                .unwrap();
+
+            tx.write_all(b"wlcs").unwrap();
+            event_loop.dispatch(Some(Duration::from_secs(1))).unwrap();
+            assert!(readable.get());
+        }
+
+        unsafe { ffi_dispatch!(wayland_server_handle(), wl_event_loop_destroy, ptr) };
+    }
+
+    #[test]
+    fn post_runs_once() {
+        let ptr = unsafe { ffi_dispatch!(wayland_server_handle(), wl_event_loop_create) };
+        assert!(!ptr.is_null());
+
+        {
+            let event_loop = unsafe { EventLoop::from_ptr(ptr) };
+            let runs = Rc::new(Cell::new(0));
+            event_loop
+                .post({
+                    let runs = runs.clone();
+                    move || runs.set(runs.get() + 1)
+                })
+                .unwrap();
+            assert_eq!(runs.get(), 0);
+
+            event_loop.dispatch(Some(Duration::ZERO)).unwrap();
+            event_loop.dispatch(Some(Duration::ZERO)).unwrap();
+            assert_eq!(runs.get(), 1);
+        }
+
+        unsafe { ffi_dispatch!(wayland_server_handle(), wl_event_loop_destroy, ptr) };
+    }
+}
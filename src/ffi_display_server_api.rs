@@ -8,9 +8,23 @@
 
 #![allow(non_camel_case_types, non_snake_case)]
 
+use std::{
+    borrow::Cow,
+    ffi::{CStr, CString},
+};
+
 use wayland_sys::{client::*, server as ssys};
 
-use crate::{ffi_pointer_api::WlcsPointer, ffi_touch_api::WlcsTouch};
+use crate::{ffi_pointer_api::WlcsPointer, ffi_touch_api::WlcsTouch, WlcsError};
+
+#[doc = " Version of WlcsIntegrationDescriptor this crate implements"]
+pub const WLCS_INTEGRATION_DESCRIPTOR_VERSION: u32 = 1;
+
+#[doc = " Version of WlcsDisplayServer this crate implements"]
+pub const WLCS_DISPLAY_SERVER_VERSION: u32 = 3;
+
+#[doc = " Version of WlcsServerIntegration this crate implements"]
+pub const WLCS_SERVER_INTEGRATION_VERSION: u32 = 1;
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -122,6 +136,311 @@ fn bindgen_test_layout_WlcsIntegrationDescriptor() {
 unsafe impl Sync for WlcsIntegrationDescriptor {}
 unsafe impl Send for WlcsIntegrationDescriptor {}
 
+impl WlcsIntegrationDescriptor {
+    /// Create a descriptor advertising `extensions`, keeping `num_extensions` in sync with the slice.
+    ///
+    /// The layout matches version 1 of `WlcsIntegrationDescriptor` in the WLCS headers, which has no fields
+    /// beyond `version`, `num_extensions` and `supported_extensions`. Should a later version add fields, this is
+    /// where they get their defaults, so descriptors built through it keep a valid layout.
+    pub const fn new(extensions: &'static [WlcsExtensionDescriptor]) -> Self {
+        WlcsIntegrationDescriptor {
+            version: WLCS_INTEGRATION_DESCRIPTOR_VERSION,
+            num_extensions: extensions.len(),
+            supported_extensions: extensions.as_ptr(),
+        }
+    }
+
+    /// Check that the descriptor matches the ABI expected by WLCS.
+    ///
+    /// This checks the `version` and that `supported_extensions` is set when `num_extensions` is not zero.
+    /// Descriptors created through [`WlcsIntegrationDescriptor::new`] always have a matching `num_extensions`.
+    pub fn validate(&self) -> Result<(), WlcsError> {
+        if self.version != WLCS_INTEGRATION_DESCRIPTOR_VERSION {
+            return Err(format!(
+                "descriptor version is {}, expected {}",
+                self.version, WLCS_INTEGRATION_DESCRIPTOR_VERSION
+            )
+            .into());
+        }
+        if self.num_extensions != 0 && self.supported_extensions.is_null() {
+            return Err(format!(
+                "descriptor advertises {} extensions but supported_extensions is null",
+                self.num_extensions
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Iterate over the `(name, version)` pairs of the supported extensions.
+    ///
+    /// Names which are not valid UTF-8 are converted lossily.
+    ///
+    /// # Safety
+    ///
+    /// `supported_extensions` must point to `num_extensions` descriptors, each with a `name` pointing to a
+    /// nul-terminated string, which stay valid for the lifetime of `self`.
+    pub unsafe fn extensions(&self) -> impl Iterator<Item = (Cow<'_, str>, u32)> {
+        unsafe { self.raw_extensions() }.iter().map(|extension| {
+            let name = unsafe { CStr::from_ptr(extension.name) };
+            (name.to_string_lossy(), extension.version)
+        })
+    }
+
+    /// The `supported_extensions` array as a slice.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`WlcsIntegrationDescriptor::extensions`].
+    unsafe fn raw_extensions(&self) -> &[WlcsExtensionDescriptor] {
+        if self.num_extensions == 0 {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.supported_extensions, self.num_extensions) }
+        }
+    }
+
+    /// The advertised version of extension `name`, e.g. `xdg_wm_base`, or `None` if it is not advertised.
+    ///
+    /// Names are compared byte for byte up to their nul terminator, so a name which is not valid UTF-8 never
+    /// matches, unlike its lossy conversion in [`WlcsIntegrationDescriptor::extensions`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`WlcsIntegrationDescriptor::extensions`].
+    pub unsafe fn extension_version(&self, name: &str) -> Option<u32> {
+        unsafe { self.raw_extensions() }
+            .iter()
+            .find(|extension| {
+                unsafe { CStr::from_ptr(extension.name) }.to_bytes() == name.as_bytes()
+            })
+            .map(|extension| extension.version)
+    }
+
+    /// The advertised version of `wl_seat`, or `None` if it is not advertised.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`WlcsIntegrationDescriptor::extensions`].
+    pub unsafe fn seat_version(&self) -> Option<SeatVersion> {
+        unsafe { self.extension_version("wl_seat") }.map(SeatVersion)
+    }
+
+    /// Copy the descriptor and its extension names into a [`DescriptorManifest`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`WlcsIntegrationDescriptor::extensions`].
+    pub unsafe fn to_manifest(&self) -> DescriptorManifest {
+        DescriptorManifest {
+            version: self.version,
+            extensions: unsafe { self.extensions() }
+                .map(|(name, version)| ExtensionManifest {
+                    name: name.into_owned(),
+                    version,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A version of `wl_seat`, which is also the version of the `wl_pointer` and `wl_touch` objects clients get
+/// from it.
+///
+/// The version advertised in the descriptor determines which input events WLCS clients expect, so the devices of
+/// an integration should deliver all the events of that version: advertising version 8 while only sending the
+/// events of version 1 fails the tests checking for frames or axis sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SeatVersion(pub u32);
+
+impl SeatVersion {
+    /// Whether pointer events are grouped by `wl_pointer.frame` (version 5).
+    pub const fn has_pointer_frame(self) -> bool {
+        self.0 >= 5
+    }
+
+    /// Whether scrolling is described by `wl_pointer.axis_source`, `axis_stop` and `axis_discrete` (version 5).
+    pub const fn has_axis_source(self) -> bool {
+        self.0 >= 5
+    }
+
+    /// Whether touch points report `wl_touch.shape` and `wl_touch.orientation` (version 6).
+    pub const fn has_touch_shape(self) -> bool {
+        self.0 >= 6
+    }
+
+    /// Whether discrete scrolling is reported by `wl_pointer.axis_value120` instead of `axis_discrete`
+    /// (version 8).
+    pub const fn has_axis_value120(self) -> bool {
+        self.0 >= 8
+    }
+}
+
+/// An owned copy of an integration descriptor, e.g. to emit a machine-readable manifest of the protocols an
+/// integration supports.
+///
+/// With the `serde` feature, it implements `Serialize`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DescriptorManifest {
+    /// Version of the descriptor struct
+    pub version: u32,
+    /// Supported extensions
+    pub extensions: Vec<ExtensionManifest>,
+}
+
+/// An extension in a [`DescriptorManifest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExtensionManifest {
+    /// Protocol name of the extension, converted lossily if it is not valid UTF-8
+    pub name: String,
+    /// Maximum version of the extension supported
+    pub version: u32,
+}
+
+/// An integration descriptor computed at runtime, e.g. to lower advertised protocol versions depending on what
+/// the compositor negotiated.
+///
+/// The descriptor owns its extension names and array, and [`DynamicDescriptor::descriptor`] can be returned from
+/// [`Wlcs::get_descriptor`](crate::Wlcs::get_descriptor). The pointers WLCS receives stay valid until the
+/// descriptor is next modified or dropped, so it should only be modified when WLCS is not reading it, e.g. in
+/// [`Wlcs::new`](crate::Wlcs::new) or [`Wlcs::start`](crate::Wlcs::start), and WLCS must fetch it again
+/// afterwards. Moving the `DynamicDescriptor` does not invalidate the pointers, as the descriptor itself is kept
+/// in a [`PinnedDescriptor`].
+#[derive(Debug)]
+pub struct DynamicDescriptor {
+    names: Vec<CString>,
+    extensions: Vec<WlcsExtensionDescriptor>,
+    descriptor: PinnedDescriptor,
+}
+
+impl DynamicDescriptor {
+    /// Create a descriptor advertising the given `(name, version)` pairs.
+    ///
+    /// Fails if a name contains a nul byte.
+    pub fn new<N: Into<Vec<u8>>>(
+        extensions: impl IntoIterator<Item = (N, u32)>,
+    ) -> Result<Self, WlcsError> {
+        let mut descriptor = DynamicDescriptor {
+            names: Vec::new(),
+            extensions: Vec::new(),
+            descriptor: PinnedDescriptor::new(WlcsIntegrationDescriptor::new(&[])),
+        };
+        descriptor.rebuild(extensions)?;
+        Ok(descriptor)
+    }
+
+    /// Replace all advertised extensions.
+    ///
+    /// Fails if a name contains a nul byte, in which case the descriptor is left unchanged.
+    pub fn rebuild<N: Into<Vec<u8>>>(
+        &mut self,
+        extensions: impl IntoIterator<Item = (N, u32)>,
+    ) -> Result<(), WlcsError> {
+        let (names, versions): (Vec<_>, Vec<_>) = extensions
+            .into_iter()
+            .map(|(name, version)| Ok((CString::new(name)?, version)))
+            .collect::<Result<Vec<_>, std::ffi::NulError>>()
+            .map_err(|err| WlcsError::new(format!("invalid extension name: {}", err)))?
+            .into_iter()
+            .unzip();
+        self.names = names;
+        self.extensions = self
+            .names
+            .iter()
+            .zip(versions)
+            .map(|(name, version)| WlcsExtensionDescriptor {
+                name: name.as_ptr(),
+                version,
+            })
+            .collect();
+        self.descriptor.set(WlcsIntegrationDescriptor {
+            num_extensions: self.extensions.len(),
+            supported_extensions: self.extensions.as_ptr(),
+            ..*self.descriptor.descriptor()
+        });
+        Ok(())
+    }
+
+    /// Change the advertised version of extension `name`.
+    ///
+    /// Returns `false` if the extension is not advertised.
+    pub fn set_version(&mut self, name: &str, version: u32) -> bool {
+        let index = self
+            .names
+            .iter()
+            .position(|candidate| candidate.as_bytes() == name.as_bytes());
+        match index {
+            Some(index) => {
+                self.extensions[index].version = version;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The descriptor to hand to WLCS.
+    pub fn descriptor(&self) -> &WlcsIntegrationDescriptor {
+        self.descriptor.descriptor()
+    }
+
+    /// The advertised version of extension `name`, see [`WlcsIntegrationDescriptor::extension_version`].
+    ///
+    /// Unlike the lookup on the raw descriptor, this is safe, as the descriptor points into data owned by self.
+    pub fn extension_version(&self, name: &str) -> Option<u32> {
+        // SAFETY: the descriptor points into the names and extensions owned by self.
+        unsafe { self.descriptor().extension_version(name) }
+    }
+
+    /// The advertised version of `wl_seat`, see [`WlcsIntegrationDescriptor::seat_version`].
+    pub fn seat_version(&self) -> Option<SeatVersion> {
+        // SAFETY: the descriptor points into the names and extensions owned by self.
+        unsafe { self.descriptor().seat_version() }
+    }
+
+    /// Copy the descriptor into a [`DescriptorManifest`].
+    pub fn to_manifest(&self) -> DescriptorManifest {
+        // SAFETY: the descriptor points into the names and extensions owned by self.
+        unsafe { self.descriptor().to_manifest() }
+    }
+}
+
+/// An integration descriptor at a stable address, for integrations computing their descriptor at runtime.
+///
+/// WLCS keeps the pointer returned by [`Wlcs::get_descriptor`](crate::Wlcs::get_descriptor), so the descriptor
+/// must not move while WLCS may read it. A descriptor stored inline in a field of the integration moves along
+/// with it, e.g. when the field is replaced through [`std::mem::replace`] or the integration is moved out of a
+/// container, leaving WLCS with a dangling pointer. A `PinnedDescriptor` keeps the descriptor on the heap, so
+/// moving it or the integration does not change the address handed to WLCS, and [`PinnedDescriptor::set`]
+/// updates it in place. Descriptors in a `static`, e.g. built with
+/// [`extension_list!`](crate::extension_list), already have a stable address.
+#[derive(Debug)]
+pub struct PinnedDescriptor {
+    descriptor: Box<WlcsIntegrationDescriptor>,
+}
+
+impl PinnedDescriptor {
+    /// Move `descriptor` to a stable address.
+    pub fn new(descriptor: WlcsIntegrationDescriptor) -> Self {
+        PinnedDescriptor {
+            descriptor: Box::new(descriptor),
+        }
+    }
+
+    /// Replace the descriptor, keeping its address.
+    ///
+    /// As with [`DynamicDescriptor`], WLCS must not be reading the descriptor while it is replaced.
+    pub fn set(&mut self, descriptor: WlcsIntegrationDescriptor) {
+        *self.descriptor = descriptor;
+    }
+
+    /// The descriptor to hand to WLCS, whose address does not change for the lifetime of `self`.
+    pub fn descriptor(&self) -> &WlcsIntegrationDescriptor {
+        &self.descriptor
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 #[allow(missing_docs)]
@@ -333,3 +652,140 @@ fn bindgen_test_layout_WlcsServerIntegration() {
         )
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension_list;
+
+    #[test]
+    fn extensions_roundtrip() {
+        static EXTENSIONS: &[WlcsExtensionDescriptor] =
+            extension_list!(("wl_compositor", 4), ("xdg_wm_base", 3));
+        let descriptor = WlcsIntegrationDescriptor::new(EXTENSIONS);
+
+        let extensions = unsafe { descriptor.extensions() }
+            .map(|(name, version)| (name.into_owned(), version))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            extensions,
+            [("wl_compositor".into(), 4), ("xdg_wm_base".into(), 3)]
+        );
+    }
+
+    #[test]
+    fn seat_version() {
+        static EXTENSIONS: &[WlcsExtensionDescriptor] =
+            extension_list!(("wl_compositor", 4), ("wl_seat", 7));
+        let descriptor = WlcsIntegrationDescriptor::new(EXTENSIONS);
+
+        let version = unsafe { descriptor.seat_version() }.unwrap();
+        assert_eq!(version, SeatVersion(7));
+        assert!(version.has_pointer_frame() && version.has_touch_shape());
+        assert!(!version.has_axis_value120());
+        assert_eq!(unsafe { descriptor.extension_version("xdg_wm_base") }, None);
+    }
+
+    #[test]
+    fn extension_version() {
+        static EXTENSIONS: &[WlcsExtensionDescriptor] = &[
+            WlcsExtensionDescriptor {
+                name: b"wl_seat\xff\0".as_ptr() as *const _,
+                version: 7,
+            },
+            WlcsExtensionDescriptor {
+                name: b"xdg_wm_base\0".as_ptr() as *const _,
+                version: 3,
+            },
+        ];
+        let descriptor = WlcsIntegrationDescriptor::new(EXTENSIONS);
+        assert_eq!(
+            unsafe { descriptor.extension_version("xdg_wm_base") },
+            Some(3)
+        );
+        assert_eq!(unsafe { descriptor.extension_version("xdg_wm") }, None);
+        assert_eq!(unsafe { descriptor.extension_version("wl_seat") }, None);
+        assert_eq!(
+            unsafe { descriptor.extension_version("wl_seat\u{fffd}") },
+            None
+        );
+
+        let dynamic = DynamicDescriptor::new([("wl_compositor", 4)]).unwrap();
+        assert_eq!(dynamic.extension_version("wl_compositor"), Some(4));
+        assert_eq!(dynamic.extension_version("wl_seat"), None);
+    }
+
+    #[test]
+    fn dynamic_descriptor() {
+        let mut descriptor =
+            DynamicDescriptor::new([("wl_compositor", 4), ("xdg_wm_base", 3)]).unwrap();
+        assert!(descriptor.set_version("xdg_wm_base", 2));
+        assert!(!descriptor.set_version("wl_shell", 1));
+        assert_eq!(
+            descriptor.to_manifest().extensions[1],
+            ExtensionManifest {
+                name: "xdg_wm_base".into(),
+                version: 2
+            }
+        );
+
+        let extensions = unsafe { descriptor.descriptor().extensions() }
+            .map(|(name, version)| (name.into_owned(), version))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            extensions,
+            [("wl_compositor".into(), 4), ("xdg_wm_base".into(), 2)]
+        );
+
+        assert!(descriptor.rebuild([("wl_\0seat", 7)]).is_err());
+        assert_eq!(descriptor.descriptor().num_extensions, 2);
+        descriptor.rebuild([("wl_seat", 7)]).unwrap();
+        assert_eq!(descriptor.descriptor().num_extensions, 1);
+        assert!(descriptor.descriptor().validate().is_ok());
+    }
+
+    #[test]
+    fn pinned_descriptor() {
+        static EXTENSIONS: &[WlcsExtensionDescriptor] = extension_list!(("wl_seat", 7));
+        let mut pinned = PinnedDescriptor::new(WlcsIntegrationDescriptor::new(&[]));
+        let address = pinned.descriptor() as *const WlcsIntegrationDescriptor;
+
+        pinned.set(WlcsIntegrationDescriptor::new(EXTENSIONS));
+        let moved = vec![pinned].pop().unwrap();
+        assert_eq!(moved.descriptor() as *const _, address);
+        assert_eq!(moved.descriptor().num_extensions, 1);
+
+        let dynamic = DynamicDescriptor::new([("wl_compositor", 4)]).unwrap();
+        let address = dynamic.descriptor() as *const WlcsIntegrationDescriptor;
+        let moved = Box::new(dynamic);
+        assert_eq!(moved.descriptor() as *const _, address);
+    }
+
+    #[test]
+    fn extensions_empty() {
+        let descriptor = WlcsIntegrationDescriptor {
+            version: 1,
+            num_extensions: 0,
+            supported_extensions: std::ptr::null(),
+        };
+        assert_eq!(unsafe { descriptor.extensions() }.count(), 0);
+    }
+
+    #[test]
+    fn validate() {
+        assert!(WlcsIntegrationDescriptor::new(&[]).validate().is_ok());
+
+        let descriptor = WlcsIntegrationDescriptor {
+            version: 2,
+            ..WlcsIntegrationDescriptor::new(&[])
+        };
+        assert!(descriptor.validate().is_err());
+
+        let descriptor = WlcsIntegrationDescriptor {
+            version: 1,
+            num_extensions: 1,
+            supported_extensions: std::ptr::null(),
+        };
+        assert!(descriptor.validate().is_err());
+    }
+}
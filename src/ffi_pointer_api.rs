@@ -9,6 +9,55 @@
 
 use wayland_sys::common::wl_fixed_t;
 
+#[doc = " Version of WlcsPointer this crate implements"]
+pub const WLCS_POINTER_VERSION: u32 = 1;
+
+#[doc = " Linux input button code of the left mouse button"]
+pub const BTN_LEFT: i32 = 0x110;
+#[doc = " Linux input button code of the right mouse button"]
+pub const BTN_RIGHT: i32 = 0x111;
+#[doc = " Linux input button code of the middle mouse button"]
+pub const BTN_MIDDLE: i32 = 0x112;
+#[doc = " Linux input button code of the side (back) mouse button"]
+pub const BTN_SIDE: i32 = 0x113;
+#[doc = " Linux input button code of the extra (forward) mouse button"]
+pub const BTN_EXTRA: i32 = 0x114;
+#[doc = " Linux input button code of the forward mouse button"]
+pub const BTN_FORWARD: i32 = 0x115;
+#[doc = " Linux input button code of the back mouse button"]
+pub const BTN_BACK: i32 = 0x116;
+#[doc = " Linux input button code of the task mouse button"]
+pub const BTN_TASK: i32 = 0x117;
+
+#[doc = " `wl_pointer.axis` value of the vertical axis"]
+pub const AXIS_VERTICAL_SCROLL: u32 = 0;
+#[doc = " `wl_pointer.axis` value of the horizontal axis"]
+pub const AXIS_HORIZONTAL_SCROLL: u32 = 1;
+
+#[doc = " `wl_pointer.axis_source` value of a scroll wheel, scrolling in discrete steps"]
+pub const AXIS_SOURCE_WHEEL: u32 = 0;
+#[doc = " `wl_pointer.axis_source` value of a finger on a touch surface, e.g. a touchpad, ended by `axis_stop`"]
+pub const AXIS_SOURCE_FINGER: u32 = 1;
+#[doc = " `wl_pointer.axis_source` value of continuous movement without a terminating event, e.g. button-based\n scrolling"]
+pub const AXIS_SOURCE_CONTINUOUS: u32 = 2;
+#[doc = " `wl_pointer.axis_source` value of a side-to-side tilt of a scroll wheel (since version 6)"]
+pub const AXIS_SOURCE_WHEEL_TILT: u32 = 3;
+
+#[doc = " Name of a mouse button code as passed to `button_up`/`button_down`, e.g. `\"BTN_LEFT\"`, for readable test\n output. Returns `None` for codes which are not mouse buttons."]
+pub fn button_name(code: i32) -> Option<&'static str> {
+    Some(match code {
+        BTN_LEFT => "BTN_LEFT",
+        BTN_RIGHT => "BTN_RIGHT",
+        BTN_MIDDLE => "BTN_MIDDLE",
+        BTN_SIDE => "BTN_SIDE",
+        BTN_EXTRA => "BTN_EXTRA",
+        BTN_FORWARD => "BTN_FORWARD",
+        BTN_BACK => "BTN_BACK",
+        BTN_TASK => "BTN_TASK",
+        _ => return None,
+    })
+}
+
 #[doc = " An object to manipulate the server's pointer state"]
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -173,3 +222,15 @@ fn bindgen_test_layout___va_list_tag() {
         )
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn button_names() {
+        assert_eq!(button_name(0x110), Some("BTN_LEFT"));
+        assert_eq!(button_name(BTN_EXTRA), Some("BTN_EXTRA"));
+        assert_eq!(button_name(0), None);
+    }
+}
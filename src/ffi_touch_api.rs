@@ -6,6 +6,9 @@
 
 use wayland_sys::common::wl_fixed_t;
 
+#[doc = " Version of WlcsTouch this crate implements"]
+pub const WLCS_TOUCH_VERSION: u32 = 1;
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct WlcsTouch {
@@ -1,10 +1,36 @@
 //!
 //! Wrappers/helpers for setting up WLCS server integration
 //!
+//! # Process-wide state
+//!
+//! Several display servers may live in one process at the same time, e.g. for tests involving two compositors,
+//! and each [`Wlcs`] value only ever sees its own calls. A few things are shared by all of them, all guarded by
+//! atomics or mutexes so that servers on different threads can use them concurrently:
+//!
+//! - the disposition of `SIGPIPE`, which is process-wide by nature. It is set when the first server is created,
+//!   and only set again if [`set_sigpipe_handling`] changed the mode in between;
+//! - the settings of [`set_sigpipe_handling`], [`set_device_retry_policy`] and [`set_panic_formatter`];
+//! - the device counters of [`device_stats`] and the registry of [`device_handles`], which cover the devices
+//!   of all servers. The registry records the server of each device, so destroying a server only reports the
+//!   devices of that server which were never destroyed;
+//! - the sockets tracked for [`socket::check_fd_balance`], which also covers all servers. They cannot be told
+//!   apart by server, so destroying a server only checks them once no other server is alive;
+//! - the environment variables configuring diagnostics, which are read once.
+//!
 
 use std::{
-    ffi::{c_char, c_int},
-    os::fd::IntoRawFd,
+    any::Any,
+    ffi::{c_char, c_int, CStr, OsStr, OsString},
+    os::{
+        fd::{AsFd, AsRawFd, IntoRawFd},
+        unix::ffi::OsStrExt,
+    },
+    sync::{
+        atomic::{AtomicU8, AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
 use container_of::container_of;
@@ -15,15 +41,414 @@ use wayland_sys::{
 };
 
 use crate::{
-    ffi_display_server_api::{WlcsDisplayServer, WlcsIntegrationDescriptor, WlcsServerIntegration},
-    ffi_pointer_api::WlcsPointer,
-    ffi_touch_api::WlcsTouch,
-    Pointer, Touch, Wlcs,
+    diagnostics,
+    event_loop::EventLoop,
+    ffi_display_server_api::{
+        WlcsDisplayServer, WlcsExtensionDescriptor, WlcsIntegrationDescriptor,
+        WlcsServerIntegration, WLCS_DISPLAY_SERVER_VERSION, WLCS_SERVER_INTEGRATION_VERSION,
+    },
+    ffi_pointer_api::{WlcsPointer, WLCS_POINTER_VERSION},
+    ffi_touch_api::{WlcsTouch, WLCS_TOUCH_VERSION},
+    fixed::Fixed,
+    geometry, socket, sys, Pointer, ServerContext, SurfaceProxy, Touch, Wlcs, WlcsError,
 };
 
+/// The name of the enclosing function, e.g. `touch_move_ffi`.
+///
+/// Deriving it keeps the contexts of diagnostics from going out of sync with the function they come from.
+macro_rules! function_name {
+    () => {{
+        fn f() {}
+        enclosing_function_name(f)
+    }};
+}
+
+/// Extract the enclosing function from the type name of the `f` item declared by [`function_name!`].
+fn enclosing_function_name<F>(_: F) -> &'static str {
+    // The type name is the path of f, like `wlcs::ffi_wrappers::touch_move_ffi::f`, with a `{{closure}}` segment
+    // per closure f is declared in, e.g. the one of `ffi_guard!`.
+    std::any::type_name::<F>()
+        .rsplit("::")
+        .skip(1)
+        .find(|segment| *segment != "{{closure}}")
+        .unwrap_or("<unknown>")
+}
+
+/// Run the body of a wrapper, catching panics so they do not unwind into WLCS.
+///
+/// A caught panic is reported with the name of the wrapper, which then returns `$on_panic` (nothing if omitted).
+/// With the `trace` feature, the call and its `args` are appended to the trace file first.
+///
+/// Wrappers delivering input events start with `input`: in dry run mode, their call is logged instead of run.
+macro_rules! ffi_guard {
+    (input, args($($arg: expr),*), $body: block) => {
+        if !diagnostics::dry_run(function_name!(), &[$(&$arg),*]) {
+            ffi_guard!(args($($arg),*), $body)
+        }
+    };
+    (args($($arg: expr),*), $body: block) => {
+        ffi_guard!(args($($arg),*), (), $body)
+    };
+    (args($($arg: expr),*), $on_panic: expr, $body: block) => {{
+        #[cfg(feature = "trace")]
+        diagnostics::trace(function_name!(), &[$(&$arg),*]);
+        match std::panic::catch_unwind(|| $body) {
+            Ok(value) => value,
+            Err(err) => {
+                diagnostics::report_panic(function_name!(), &*err);
+                $on_panic
+            }
+        }
+    }};
+}
+
+/// How `SIGPIPE` is handled when WLCS creates a display server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SigpipeMode {
+    /// Ignore `SIGPIPE`, so writing to a disconnected client fails with `EPIPE` instead of killing the process.
+    #[default]
+    Ignore,
+    /// Restore the default disposition of `SIGPIPE`.
+    Default,
+    /// Do not touch the disposition of `SIGPIPE`, e.g. because the host process manages signals itself.
+    Leave,
+}
+
+static SIGPIPE_MODE: AtomicU8 = AtomicU8::new(SigpipeMode::Ignore as u8);
+
+/// Set how `SIGPIPE` is handled by subsequent display server creations.
+///
+/// The disposition of a signal is process-wide, so this should be called before WLCS creates the first server.
+/// Defaults to [`SigpipeMode::Ignore`].
+pub fn set_sigpipe_handling(mode: SigpipeMode) {
+    SIGPIPE_MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+/// The mode last applied by [`install_sigpipe_handling`], or `None` before the first server is created.
+static SIGPIPE_INSTALLED: Mutex<Option<SigpipeMode>> = Mutex::new(None);
+
+/// Apply the [`SigpipeMode`] for a new server, unless it is already in effect.
+///
+/// Concurrent servers share the disposition, so creating more servers does not touch it again, unless the mode
+/// was changed with [`set_sigpipe_handling`].
+fn install_sigpipe_handling() -> std::io::Result<()> {
+    let mode = sigpipe_handling();
+    let mut installed = SIGPIPE_INSTALLED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if *installed != Some(mode) {
+        sys::set_sigpipe_disposition(mode)?;
+        *installed = Some(mode);
+    }
+    Ok(())
+}
+
+fn sigpipe_handling() -> SigpipeMode {
+    match SIGPIPE_MODE.load(Ordering::Relaxed) {
+        mode if mode == SigpipeMode::Default as u8 => SigpipeMode::Default,
+        mode if mode == SigpipeMode::Leave as u8 => SigpipeMode::Leave,
+        _ => SigpipeMode::Ignore,
+    }
+}
+
+/// Set the formatter used for the message reported when a panic is caught at the FFI boundary.
+///
+/// `formatter` is called with the name of the wrapper the panic was caught in and the panic payload, e.g. to
+/// emit JSON lines for a log aggregator. The message is reported like any other diagnostic. Defaults to a
+/// human-readable `panic in <context>: <message>` line.
+///
+/// # Examples
+///
+/// ```
+/// use std::any::Any;
+/// use wlcs::ffi_wrappers::panic_message;
+///
+/// fn json(context: &str, payload: &dyn Any) -> String {
+///     let message = panic_message(payload).unwrap_or("<non-string payload>");
+///     format!(r#"{{"event":"panic","context":{:?},"message":{:?}}}"#, context, message)
+/// }
+///
+/// wlcs::ffi_wrappers::set_panic_formatter(json);
+/// ```
+pub fn set_panic_formatter(formatter: fn(context: &str, payload: &dyn Any) -> String) {
+    diagnostics::set_panic_formatter(formatter);
+}
+
+/// The message of a panic payload, if it is a string as for the panics of `panic!`, `unwrap` or `expect`.
+pub fn panic_message(payload: &dyn Any) -> Option<&str> {
+    diagnostics::panic_message(payload)
+}
+
+/// Spawn a named thread to run the display server on, e.g. from [`Wlcs::start`].
+///
+/// The name shows up in debuggers and tools like `top` or `perf`. Signal dispositions are process-wide and
+/// handled when the server is created, but with [`SigpipeMode::Ignore`] `SIGPIPE` is also blocked on the new
+/// thread, so a disconnected client cannot kill the process even if the host changes the disposition later.
+///
+/// The returned handle should be stored and joined in [`Wlcs::stop`].
+pub fn spawn_server_thread<F, T>(name: impl Into<String>, f: F) -> std::io::Result<JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let block_sigpipe = sigpipe_handling() == SigpipeMode::Ignore;
+    std::thread::Builder::new()
+        .name(name.into())
+        .spawn(move || {
+            if block_sigpipe {
+                if let Err(err) = sys::block_sigpipe() {
+                    diagnostics::warn(format_args!(
+                        "spawn_server_thread: failed to block SIGPIPE: {}",
+                        err
+                    ));
+                }
+            }
+            f()
+        })
+}
+
+/// Number of input devices created and destroyed by WLCS, see [`device_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DeviceStats {
+    /// Pointers successfully created through [`Wlcs::create_pointer`]
+    pub pointers_created: usize,
+    /// Pointers destroyed by WLCS
+    pub pointers_destroyed: usize,
+    /// Touches successfully created through [`Wlcs::create_touch`]
+    pub touches_created: usize,
+    /// Touches destroyed by WLCS
+    pub touches_destroyed: usize,
+}
+
+impl DeviceStats {
+    /// Number of pointers created but not yet destroyed.
+    pub fn live_pointers(&self) -> usize {
+        self.pointers_created
+            .saturating_sub(self.pointers_destroyed)
+    }
+
+    /// Number of touches created but not yet destroyed.
+    pub fn live_touches(&self) -> usize {
+        self.touches_created.saturating_sub(self.touches_destroyed)
+    }
+}
+
+static POINTERS_CREATED: AtomicUsize = AtomicUsize::new(0);
+static POINTERS_DESTROYED: AtomicUsize = AtomicUsize::new(0);
+static TOUCHES_CREATED: AtomicUsize = AtomicUsize::new(0);
+static TOUCHES_DESTROYED: AtomicUsize = AtomicUsize::new(0);
+
+/// Get the number of input devices created and destroyed in this process so far.
+pub fn device_stats() -> DeviceStats {
+    DeviceStats {
+        pointers_created: POINTERS_CREATED.load(Ordering::Relaxed),
+        pointers_destroyed: POINTERS_DESTROYED.load(Ordering::Relaxed),
+        touches_created: TOUCHES_CREATED.load(Ordering::Relaxed),
+        touches_destroyed: TOUCHES_DESTROYED.load(Ordering::Relaxed),
+    }
+}
+
+/// Kind of an input device created by WLCS, see [`DeviceHandle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceKind {
+    /// A pointer created through [`Wlcs::create_pointer`]
+    Pointer,
+    /// A touch device created through [`Wlcs::create_touch`]
+    Touch,
+}
+
+/// A live input device created by WLCS, see [`device_handles`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeviceHandle {
+    /// Kind of the device
+    pub kind: DeviceKind,
+    /// Address of the `WlcsPointer` or `WlcsTouch` handed to WLCS, matching the pointer in traces
+    pub address: usize,
+    /// Address of the `WlcsDisplayServer` the device was created on
+    pub server: usize,
+}
+
+static LIVE_DEVICES: Mutex<Vec<DeviceHandle>> = Mutex::new(Vec::new());
+
+fn live_devices() -> std::sync::MutexGuard<'static, Vec<DeviceHandle>> {
+    LIVE_DEVICES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn register_device(kind: DeviceKind, address: usize, server: usize) {
+    live_devices().push(DeviceHandle {
+        kind,
+        address,
+        server,
+    });
+}
+
+fn unregister_device(kind: DeviceKind, address: usize) {
+    let mut devices = live_devices();
+    if let Some(index) = devices
+        .iter()
+        .position(|device| device.kind == kind && device.address == address)
+    {
+        devices.swap_remove(index);
+    }
+}
+
+/// Number of live pointers and touches created on `server`.
+fn live_devices_of(server: usize) -> (usize, usize) {
+    live_devices()
+        .iter()
+        .filter(|device| device.server == server)
+        .fold((0, 0), |(pointers, touches), device| match device.kind {
+            DeviceKind::Pointer => (pointers + 1, touches),
+            DeviceKind::Touch => (pointers, touches + 1),
+        })
+}
+
+/// Number of servers created by the wrappers and not destroyed yet.
+static LIVE_SERVERS: AtomicUsize = AtomicUsize::new(0);
+
+/// The input devices created by WLCS in this process and not yet destroyed, oldest first unless some were
+/// destroyed out of order.
+///
+/// Unlike [`device_stats`], this tells which devices are alive, e.g. to match a leaked device with the trace of
+/// its creation, or to notice that a device a test still uses was destroyed.
+pub fn device_handles() -> Vec<DeviceHandle> {
+    live_devices().clone()
+}
+
+/// How often device creation is retried before reporting failure to WLCS, see [`set_device_retry_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first failed attempt
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every following one
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Do not retry, the default.
+    pub const NONE: RetryPolicy = RetryPolicy {
+        retries: 0,
+        backoff: Duration::ZERO,
+    };
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::NONE
+    }
+}
+
+static DEVICE_RETRY_POLICY: Mutex<RetryPolicy> = Mutex::new(RetryPolicy::NONE);
+
+/// Retry [`Wlcs::create_pointer`] and [`Wlcs::create_touch`] according to `policy` when they return `None`.
+///
+/// This helps with device creation failing transiently, e.g. on loaded CI machines. Every retry is reported as a
+/// warning. Defaults to [`RetryPolicy::NONE`].
+pub fn set_device_retry_policy(policy: RetryPolicy) {
+    *DEVICE_RETRY_POLICY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = policy;
+}
+
+fn create_device_with_retry<T>(context: &str, mut create: impl FnMut() -> Option<T>) -> Option<T> {
+    let policy = *DEVICE_RETRY_POLICY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut backoff = policy.backoff;
+    for retry in 1..=policy.retries {
+        if let Some(device) = create() {
+            return Some(device);
+        }
+        diagnostics::warn(format_args!(
+            "{}: device creation failed, retry {}/{} in {:?}",
+            context, retry, policy.retries, backoff
+        ));
+        std::thread::sleep(backoff);
+        backoff = backoff.saturating_mul(2);
+    }
+    create()
+}
+
+// repr(C) places the first two fields at the same offsets for every W, see DisplayServerPrefix.
+#[repr(C)]
 struct DisplayServerHandle<W: Wlcs> {
     wlcs_display_server: WlcsDisplayServer,
+    /// `destroy_server_ffi::<W>`, for destroying servers of a type selected at runtime.
+    destroy: unsafe extern "C" fn(*mut WlcsDisplayServer),
     wlcs: W,
+    state: ServerState,
+    disabled: DisabledDevices,
+}
+
+/// Where a server is in the lifecycle WLCS drives, see [`Wlcs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ServerState {
+    /// Created but never started.
+    Created,
+    /// Started and not stopped since.
+    Running,
+    /// Stopped, and may be started again.
+    Stopped,
+}
+
+/// Device types disabled through `WLCS_RS_DISABLE`, making WLCS skip the tests using them.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct DisabledDevices {
+    pointer: bool,
+    touch: bool,
+}
+
+impl DisabledDevices {
+    fn from_env() -> Self {
+        std::env::var("WLCS_RS_DISABLE")
+            .map(|list| Self::parse(&list))
+            .unwrap_or_default()
+    }
+
+    /// Parse a comma-separated list of device types.
+    fn parse(list: &str) -> Self {
+        let mut disabled = DisabledDevices::default();
+        for device in list
+            .split(',')
+            .map(str::trim)
+            .filter(|device| !device.is_empty())
+        {
+            match device {
+                "pointer" => disabled.pointer = true,
+                "touch" => disabled.touch = true,
+                _ => diagnostics::warn(format_args!(
+                    "WLCS_RS_DISABLE: ignoring unknown device type {:?}",
+                    device
+                )),
+            }
+        }
+        disabled
+    }
+}
+
+impl<W: Wlcs> DisplayServerHandle<W> {
+    fn is_running(&self) -> bool {
+        self.state == ServerState::Running && self.wlcs.is_running()
+    }
+
+    /// Warn about a device requested while the server is not running, which WLCS does not do in a correct
+    /// fixture: devices are created after `start` and destroyed before `stop`.
+    fn check_running(&self, function: &str, device: &str) {
+        if self.is_running() {
+            return;
+        }
+        let reason = match self.state {
+            ServerState::Created => "before the server was started",
+            ServerState::Stopped => "after the server was stopped",
+            ServerState::Running => "while the integration reports the server is not running",
+        };
+        diagnostics::warn(format_args!(
+            "{}: {} requested {}",
+            function, device, reason
+        ));
+    }
 }
 
 struct PointerHandle<W: Wlcs> {
@@ -64,123 +489,325 @@ unsafe fn get_display_server_handle_ref<'a, W: Wlcs>(
     unsafe { &*container_of!(ptr, DisplayServerHandle<W>, wlcs_display_server) }
 }
 
+/// The integration behind a display server created by the wrappers, e.g. to assert its internal state in
+/// white-box tests after driving it through the functions of `ptr`.
+///
+/// Only available with the `white-box` feature.
+///
+/// # Safety
+///
+/// - `ptr` must have been returned by the `create_server` of [`wlcs_server::<W>`](wlcs_server), or the one
+///   selected for `W`, and not be destroyed yet. A server of another integration type would be reinterpreted as
+///   `W`.
+/// - The caller has picked a lifetime ending before the server is destroyed, and no call through `ptr` mutating
+///   the server may happen while the reference is held.
+#[cfg(feature = "white-box")]
+pub unsafe fn wlcs_ref<'a, W: Wlcs>(ptr: *const WlcsDisplayServer) -> &'a W {
+    unsafe { &get_display_server_handle_ref::<W>(ptr).wlcs }
+}
+
+/// Check that a [`WlcsPointer`] passed by WLCS is one built by [`wlcs_pointer`].
+///
+/// A mismatching version means the layout WLCS uses differs from ours, and the `container_of` math would be
+/// wrong.
+///
+/// # Safety
+///
+/// The pointer must be valid for reads.
+unsafe fn debug_assert_pointer_version(ptr: *const WlcsPointer) {
+    debug_assert_eq!(
+        unsafe { (*ptr).version },
+        WLCS_POINTER_VERSION,
+        "WlcsPointer version mismatch, WLCS and wlcs-rs disagree on the pointer ABI"
+    );
+}
+
+/// Check that a [`WlcsTouch`] passed by WLCS is one built by [`wlcs_touch`].
+///
+/// # Safety
+///
+/// The pointer must be valid for reads.
+unsafe fn debug_assert_touch_version(ptr: *const WlcsTouch) {
+    debug_assert_eq!(
+        unsafe { (*ptr).version },
+        WLCS_TOUCH_VERSION,
+        "WlcsTouch version mismatch, WLCS and wlcs-rs disagree on the touch ABI"
+    );
+}
+
 unsafe fn get_pointer_handle<'a, W: Wlcs>(ptr: *mut WlcsPointer) -> &'a mut PointerHandle<W> {
+    unsafe { debug_assert_pointer_version(ptr) };
     unsafe { &mut *container_of!(ptr, PointerHandle<W>, wlcs_pointer) }
 }
 
 unsafe fn get_touch_handle<'a, W: Wlcs>(ptr: *mut WlcsTouch) -> &'a mut TouchHandle<W> {
+    unsafe { debug_assert_touch_version(ptr) };
     unsafe { &mut *container_of!(ptr, TouchHandle<W>, wlcs_touch) }
 }
 
+/// Catch descriptors which would make WLCS silently skip tests: no extensions at all, or unnamed ones.
+fn check_extensions(descriptor: &WlcsIntegrationDescriptor) -> Result<(), WlcsError> {
+    descriptor.validate()?;
+    if descriptor.num_extensions == 0 {
+        return Err(
+            "descriptor advertises no extensions, tests requiring any protocol will not run".into(),
+        );
+    }
+    // SAFETY: the descriptor is about to be handed to WLCS, which relies on the same invariants.
+    if let Some(index) = unsafe { descriptor.extensions() }.position(|(name, _)| name.is_empty()) {
+        return Err(format!("descriptor extension {} has an empty name", index).into());
+    }
+    Ok(())
+}
+
+/// Names longer than this are most likely not protocol names but whatever a dangling pointer points to.
+const MAX_EXTENSION_NAME_LEN: usize = 256;
+
+/// Check that the extension array and names of `descriptor` are readable, without dereferencing them.
+///
+/// This catches descriptors pointing to freed memory before WLCS reads it. Checking needs
+/// `process_vm_readv`; if it is not permitted, nothing is checked.
+fn check_extension_pointers(descriptor: &WlcsIntegrationDescriptor) -> Result<(), WlcsError> {
+    let read = |addr: usize, buf: &mut [u8]| match sys::read_memory(addr, buf) {
+        Ok(()) => Ok(true),
+        Err(err) if err.raw_os_error() == Some(libc::EFAULT) => Ok(false),
+        Err(err) => Err(err),
+    };
+    let check = || -> std::io::Result<Result<(), WlcsError>> {
+        let size = std::mem::size_of::<WlcsExtensionDescriptor>();
+        let mut array = vec![0u8; descriptor.num_extensions * size];
+        if !read(descriptor.supported_extensions as usize, &mut array)? {
+            return Ok(Err(
+                "supported_extensions points to unreadable memory, is it dangling?".into(),
+            ));
+        }
+        for (index, extension) in array.chunks_exact(size).enumerate() {
+            // SAFETY: the chunk has the size of a descriptor, whose fields are valid for any bit pattern.
+            let extension = unsafe {
+                std::ptr::read_unaligned(extension.as_ptr().cast::<WlcsExtensionDescriptor>())
+            };
+            let mut name = Vec::new();
+            let mut addr = extension.name as usize;
+            while !name.contains(&0) && name.len() < MAX_EXTENSION_NAME_LEN {
+                // Read up to the end of the page, so that readable names are never reported as dangling.
+                let len = (sys::page_size() - addr % sys::page_size())
+                    .min(MAX_EXTENSION_NAME_LEN - name.len());
+                let mut chunk = vec![0; len];
+                if !read(addr, &mut chunk)? {
+                    return Ok(Err(format!(
+                        "name of extension {} points to unreadable memory, is it dangling?",
+                        index
+                    )
+                    .into()));
+                }
+                name.extend_from_slice(&chunk);
+                addr += len;
+            }
+            let Some(len) = name.iter().position(|&byte| byte == 0) else {
+                return Ok(Err(format!(
+                    "name of extension {} is not nul-terminated within {} bytes, is it dangling?",
+                    index, MAX_EXTENSION_NAME_LEN
+                )
+                .into()));
+            };
+            if !name[..len]
+                .iter()
+                .all(|byte| byte.is_ascii_alphanumeric() || *byte == b'_')
+            {
+                return Ok(Err(format!(
+                    "name of extension {} is not a protocol name, is it dangling?",
+                    index
+                )
+                .into()));
+            }
+        }
+        Ok(Ok(()))
+    };
+    // Without process_vm_readv, there is no way to check the pointers without dereferencing them.
+    check().unwrap_or(Ok(()))
+}
+
 #[allow(unused)]
 unsafe extern "C" fn create_server_ffi<W: Wlcs>(
-    _argc: c_int,
-    _argv: *mut *const c_char,
+    argc: c_int,
+    argv: *mut *const c_char,
 ) -> *mut WlcsDisplayServer {
-    // block the SIGPIPE signal here, we are a cdynlib so Rust does not do it for us
-    match std::panic::catch_unwind(|| {
-        use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+    ffi_guard!(args(argc), std::ptr::null_mut(), {
+        // we are a cdylib so Rust does not ignore SIGPIPE for us
+        install_sigpipe_handling().unwrap();
 
-        unsafe {
-            sigaction(
-                Signal::SIGPIPE,
-                &SigAction::new(SigHandler::SigIgn, SaFlags::empty(), SigSet::empty()),
-            )
-            .unwrap();
+        // SAFETY: WLCS passes the arguments it was started with.
+        let context = ServerContext::new(unsafe { args_from_argv(argc, argv) });
+        // A panic is the most common way for an integration to fail, e.g. on an unwrapped missing device in CI,
+        // so it is reported like an error instead of only as a caught panic.
+        let wlcs =
+            std::panic::catch_unwind(|| W::new_with_context(&context)).unwrap_or_else(|err| {
+                Err(diagnostics::panic_message(&*err)
+                    .unwrap_or("new panicked")
+                    .into())
+            });
+        let mut wlcs = match wlcs {
+            Ok(wlcs) => wlcs,
+            Err(err) => {
+                diagnostics::error(format_args!(
+                    "create_server_ffi: integration failed to initialize: {}",
+                    err
+                ));
+                return std::ptr::null_mut();
+            }
+        };
+        log_name_once(wlcs.name());
+        if let Err(err) = check_extensions(wlcs.get_descriptor()) {
+            diagnostics::warn(format_args!("create_server_ffi: {}", err));
+        }
+        let descriptor = *wlcs.get_descriptor();
+        wlcs.configure(&descriptor);
+        let mut wlcs_display_server = wlcs_display_server::<W>();
+        if !wlcs.supports_pointer() {
+            wlcs_display_server.create_pointer = None;
+        }
+        if !wlcs.supports_touch() {
+            wlcs_display_server.create_touch = None;
         }
-
-        let wlcs = W::new();
         let dsh = Box::new(DisplayServerHandle {
-            wlcs_display_server: wlcs_display_server::<W>(),
+            wlcs_display_server,
+            destroy: destroy_server_ffi::<W>,
             wlcs,
+            state: ServerState::Created,
+            disabled: DisabledDevices::from_env(),
         });
         let handle = Box::into_raw(dsh);
-        std::ptr::addr_of_mut!((*handle).wlcs_display_server)
-    }) {
-        Ok(ptr) => ptr,
-        Err(err) => {
-            println!(
-                "panic in create_server_ffi on ptr: {:p} (type {:?})",
-                err.as_ref() as *const _,
-                err.type_id()
-            );
-            std::ptr::null_mut()
-        }
+        LIVE_SERVERS.fetch_add(1, Ordering::Relaxed);
+        unsafe { std::ptr::addr_of_mut!((*handle).wlcs_display_server) }
+    })
+}
+
+/// Copy the `argc` arguments of `argv`, skipping null entries.
+///
+/// # Safety
+///
+/// `argv` must be null or point to `argc` pointers, each null or pointing to a nul-terminated string.
+unsafe fn args_from_argv(argc: c_int, argv: *mut *const c_char) -> Vec<OsString> {
+    if argv.is_null() {
+        return Vec::new();
     }
+    (0..usize::try_from(argc).unwrap_or(0))
+        .map(|i| unsafe { *argv.add(i) })
+        .filter(|arg| !arg.is_null())
+        .map(|arg| OsStr::from_bytes(unsafe { CStr::from_ptr(arg) }.to_bytes()).to_owned())
+        .collect()
 }
 
+/// Final free of the server: WLCS no longer uses it after this.
 #[allow(unused)]
 unsafe extern "C" fn destroy_server_ffi<W: Wlcs>(ptr: *mut WlcsDisplayServer) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(args(ptr), {
         // SAFETY:
         // - wlcs will no longer use the WlcsDisplayServer pointer. This ensures we take back ownership of the
         //   allocation.
         // - The DisplayServerHandle was created using Box::from_raw, ensuring the memory layout is correct.
-        let _server = unsafe {
+        let mut server = unsafe {
             Box::from_raw(container_of!(
                 ptr,
                 DisplayServerHandle::<W>,
                 wlcs_display_server
             ))
         };
-        assert_eq!(_server.wlcs_display_server.version, 3);
-    }) {
-        println!(
-            "panic in destroy_server_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
+        assert_eq!(
+            server.wlcs_display_server.version,
+            WLCS_DISPLAY_SERVER_VERSION
         );
-    }
+        server.wlcs.on_destroy();
+        drop(server);
+        let previously_live = LIVE_SERVERS
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |live| {
+                Some(live.saturating_sub(1))
+            })
+            .unwrap_or_default();
+        // Sockets are not tracked per server, so the sockets of other live servers would be reported as leaks.
+        if previously_live <= 1 {
+            socket::check_fd_balance();
+        }
+
+        let (pointers, touches) = live_devices_of(ptr as usize);
+        if pointers != 0 || touches != 0 {
+            diagnostics::warn(format_args!(
+                "destroy_server_ffi: {} pointers and {} touches were never destroyed",
+                pointers, touches
+            ));
+        }
+    })
 }
 
 #[allow(unused)]
 unsafe extern "C" fn start_server_ffi<W: Wlcs>(ptr: *mut WlcsDisplayServer) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(args(ptr), {
         let server = unsafe { get_display_server_handle_mut::<W>(ptr) };
-        assert_eq!(server.wlcs_display_server.version, 3);
-        server.wlcs.start()
-    }) {
-        println!(
-            "panic in start_server_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
+        assert_eq!(
+            server.wlcs_display_server.version,
+            WLCS_DISPLAY_SERVER_VERSION
         );
-    }
+        #[cfg(feature = "watchdog")]
+        let _watchdog = diagnostics::Watchdog::arm("start");
+        server.wlcs.start();
+        server.state = ServerState::Running;
+        if let Some(name) = server.wlcs.display_name() {
+            diagnostics::info(format_args!("start_server_ffi: listening on {}", name));
+        }
+    })
 }
 
+/// Test teardown: the server is stopped, but may be started again until it is destroyed.
 #[allow(unused)]
 unsafe extern "C" fn stop_server_ffi<W: Wlcs>(ptr: *mut WlcsDisplayServer) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(args(ptr), {
         let server = unsafe { get_display_server_handle_mut::<W>(ptr) };
-        assert_eq!(server.wlcs_display_server.version, 3);
-        server.wlcs.stop();
-    }) {
-        println!(
-            "panic in stop_server_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
+        assert_eq!(
+            server.wlcs_display_server.version,
+            WLCS_DISPLAY_SERVER_VERSION
         );
-    }
+        server.state = ServerState::Stopped;
+        if let Err(err) = server.wlcs.stop() {
+            diagnostics::error(format_args!(
+                "stop_server_ffi: failed to stop server: {}",
+                err
+            ));
+        }
+    })
 }
 
 #[allow(unused)]
 unsafe extern "C" fn create_client_socket_ffi<W: Wlcs>(ptr: *mut WlcsDisplayServer) -> c_int {
-    match std::panic::catch_unwind(|| {
+    ffi_guard!(args(ptr), -1, {
         let server = unsafe { get_display_server_handle_mut::<W>(ptr) };
-        assert_eq!(server.wlcs_display_server.version, 3);
-        server.wlcs.create_client_socket()
-    }) {
-        // WLCS takes ownership of the file descriptor for the client socket.
-        Ok(client) => client.map_or(-1, |c| c.into_raw_fd()),
-        Err(err) => {
-            println!(
-                "panic in wlcs_display_server::create_client_socket_ffi on ptr: {:p} (type {:?})",
-                err.as_ref() as *const _,
-                err.type_id()
-            );
-            -1
+        assert_eq!(
+            server.wlcs_display_server.version,
+            WLCS_DISPLAY_SERVER_VERSION
+        );
+        let client = server.wlcs.create_client_socket().and_then(|client| {
+            server.wlcs.configure_client_socket(client.as_fd())?;
+            Ok(client)
+        });
+        match client {
+            Ok(client) => {
+                debug_assert!(
+                    sys::fd_is_open(client.as_fd()),
+                    "create_client_socket returned a closed fd"
+                );
+                // WLCS takes ownership of the file descriptor for the client socket. This is the only place it
+                // is released from its OwnedFd, so it cannot be closed behind WLCS' back.
+                server.wlcs.on_client_connected(client.as_raw_fd());
+                client.into_raw_fd()
+            }
+            Err(err) => {
+                diagnostics::error(format_args!(
+                    "create_client_socket_ffi: failed to create client socket: {}",
+                    err
+                ));
+                -1
+            }
         }
-    }
+    })
 }
 
 unsafe extern "C" fn position_window_absolute_ffi<W: Wlcs>(
@@ -190,113 +817,203 @@ unsafe extern "C" fn position_window_absolute_ffi<W: Wlcs>(
     x: c_int,
     y: c_int,
 ) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(args(ptr, display, surface, x, y), {
         let server = unsafe { get_display_server_handle_mut::<W>(ptr) };
-        assert_eq!(server.wlcs_display_server.version, 3);
-        server.wlcs.position_window_absolute(display, surface, x, y);
-    }) {
-        println!(
-            "panic in wlcs_display_server::position_window_absolute_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
+        assert_eq!(
+            server.wlcs_display_server.version,
+            WLCS_DISPLAY_SERVER_VERSION
         );
-    }
+        // SAFETY: WLCS passes a wl_surface proxy which outlives this call.
+        let surface = unsafe { SurfaceProxy::from_ptr(surface) };
+        if geometry::checked_position(x, y).is_none() {
+            diagnostics::warn(format_args!(
+                "position_window_absolute_ffi: absurd position ({}, {}), passing it on anyway",
+                x, y
+            ));
+        }
+        server.wlcs.position_window_absolute(display, surface, x, y);
+    })
 }
 
 #[allow(unused)]
 unsafe extern "C" fn create_pointer_ffi<W: Wlcs>(ptr: *mut WlcsDisplayServer) -> *mut WlcsPointer {
-    match std::panic::catch_unwind(|| {
+    ffi_guard!(args(ptr), std::ptr::null_mut(), {
         let server = unsafe { get_display_server_handle_mut::<W>(ptr) };
-        assert_eq!(server.wlcs_display_server.version, 3);
-        let Some(p) = server.wlcs.create_pointer() else {
+        assert_eq!(
+            server.wlcs_display_server.version,
+            WLCS_DISPLAY_SERVER_VERSION
+        );
+        if server.disabled.pointer {
+            return std::ptr::null_mut();
+        }
+        server.check_running("create_pointer_ffi", "pointer");
+        let Some(mut p) = create_device_with_retry("create_pointer_ffi", || {
+            server.wlcs.create_pointer_on_seat(0)
+        }) else {
+            diagnostics::info(format_args!(
+                "create_pointer_ffi: the integration provided no pointer"
+            ));
             return std::ptr::null_mut();
         };
 
+        p.set_interface_version(WLCS_POINTER_VERSION);
+        POINTERS_CREATED.fetch_add(1, Ordering::Relaxed);
         let handle: *mut PointerHandle<W> = Box::into_raw(Box::new(PointerHandle {
             wlcs_pointer: wlcs_pointer::<W>(),
             p,
         }));
-        std::ptr::addr_of_mut!((*handle).wlcs_pointer)
-    }) {
-        Ok(ptr) => ptr,
-        Err(err) => {
-            println!(
-                "panic in wlcs_display_server::create_pointer_ffi on ptr: {:p} (type {:?})",
-                err.as_ref() as *const _,
-                err.type_id()
-            );
-            std::ptr::null_mut()
-        }
-    }
+        let wlcs_pointer = unsafe { std::ptr::addr_of_mut!((*handle).wlcs_pointer) };
+        register_device(DeviceKind::Pointer, wlcs_pointer as usize, ptr as usize);
+        wlcs_pointer
+    })
 }
 
 #[allow(unused)]
 unsafe extern "C" fn create_touch_ffi<W: Wlcs>(ptr: *mut WlcsDisplayServer) -> *mut WlcsTouch {
-    match std::panic::catch_unwind(|| {
+    ffi_guard!(args(ptr), std::ptr::null_mut(), {
         let server = unsafe { get_display_server_handle_mut::<W>(ptr) };
-        assert_eq!(server.wlcs_display_server.version, 3);
-        let Some(t) = server.wlcs.create_touch() else {
+        assert_eq!(
+            server.wlcs_display_server.version,
+            WLCS_DISPLAY_SERVER_VERSION
+        );
+        if server.disabled.touch {
+            return std::ptr::null_mut();
+        }
+        server.check_running("create_touch_ffi", "touch");
+        let Some(mut t) =
+            create_device_with_retry("create_touch_ffi", || server.wlcs.create_touch_on_seat(0))
+        else {
+            diagnostics::info(format_args!(
+                "create_touch_ffi: the integration provided no touch device"
+            ));
             return std::ptr::null_mut();
         };
+        t.set_interface_version(WLCS_TOUCH_VERSION);
+        TOUCHES_CREATED.fetch_add(1, Ordering::Relaxed);
         let handle: *mut TouchHandle<W> = Box::into_raw(Box::new(TouchHandle {
             wlcs_touch: wlcs_touch::<W>(),
             t,
         }));
-        std::ptr::addr_of_mut!((*handle).wlcs_touch)
-    }) {
-        Ok(ptr) => ptr,
-        Err(err) => {
-            println!(
-                "panic in wlcs_display_server::create_touch_ffi on ptr: {:p} (type {:?})",
-                err.as_ref() as *const _,
-                err.type_id()
-            );
-            std::ptr::null_mut()
-        }
+        let wlcs_touch = unsafe { std::ptr::addr_of_mut!((*handle).wlcs_touch) };
+        register_device(DeviceKind::Touch, wlcs_touch as usize, ptr as usize);
+        wlcs_touch
+    })
+}
+
+/// Log the name of an integration the first time a server with this name is created.
+fn log_name_once(name: &str) {
+    // Statics are shared between all the integration types, so the names logged are tracked together.
+    static LOGGED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    let mut logged = LOGGED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if !logged.iter().any(|logged| logged == name) {
+        diagnostics::info(format_args!("create_server_ffi: integration {}", name));
+        logged.push(name.to_owned());
     }
 }
 
+/// Print the advertised extensions the first time the descriptor is fetched, if `WLCS_RS_DUMP_DESCRIPTOR=1`.
+fn dump_descriptor_once(descriptor: &WlcsIntegrationDescriptor) {
+    static DUMP: std::sync::Once = std::sync::Once::new();
+    DUMP.call_once(|| {
+        if std::env::var_os("WLCS_RS_DUMP_DESCRIPTOR").map_or(true, |var| var != "1") {
+            return;
+        }
+        if let Err(err) = descriptor.validate() {
+            diagnostics::warn(format_args!("not dumping invalid descriptor: {}", err));
+            return;
+        }
+
+        // SAFETY: the descriptor is handed to WLCS, which relies on the same invariants.
+        let extensions: Vec<_> = unsafe { descriptor.extensions() }.collect();
+        let width = extensions
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(Some("extension".len()))
+            .max()
+            .unwrap_or_default();
+        let mut table = format!(
+            "integration descriptor v{}, {} extensions:\n{:<width$}  version",
+            descriptor.version,
+            extensions.len(),
+            "extension",
+            width = width
+        );
+        for (name, version) in &extensions {
+            table.push_str(&format!("\n{:<width$}  {}", name, version, width = width));
+        }
+        diagnostics::info(format_args!("{}", table));
+    });
+}
+
 #[allow(unused)]
 unsafe extern "C" fn get_descriptor_ffi<W: Wlcs>(
     ptr: *const WlcsDisplayServer,
 ) -> *const WlcsIntegrationDescriptor {
-    match std::panic::catch_unwind(|| {
+    ffi_guard!(args(ptr), std::ptr::null(), {
         let server = unsafe { get_display_server_handle_ref::<W>(ptr) };
-        server.wlcs.get_descriptor()
-    }) {
-        Ok(ptr) => ptr as *const WlcsIntegrationDescriptor,
-        Err(err) => {
-            println!(
-                "panic in wlcs_display_server::get_descriptor_ffi on ptr: {:p} (type {:?})",
-                err.as_ref() as *const _,
-                err.type_id()
-            );
-            std::ptr::null_mut()
+        let descriptor = server.wlcs.get_descriptor();
+        if cfg!(debug_assertions) {
+            if let Err(err) = descriptor
+                .validate()
+                .and_then(|()| check_extension_pointers(descriptor))
+            {
+                diagnostics::error(format_args!(
+                    "get_descriptor_ffi: invalid integration descriptor: {}",
+                    err
+                ));
+            }
         }
-    }
+        dump_descriptor_once(descriptor);
+        descriptor as *const WlcsIntegrationDescriptor
+    })
 }
 
+/// A `start_on_this_thread` returning faster than this most likely did not run the server at all.
+const START_ON_THIS_THREAD_MIN_DURATION: Duration = Duration::from_millis(5);
+
 #[allow(unused)]
 unsafe extern "C" fn start_on_this_thread_ffi<W: Wlcs>(
     ptr: *mut WlcsDisplayServer,
     event_loop: *mut wl_event_loop,
 ) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(args(ptr, event_loop), {
         let server = unsafe { get_display_server_handle_mut::<W>(ptr) };
-        assert_eq!(server.wlcs_display_server.version, 3);
-        server.wlcs.start_on_this_thread(event_loop)
-    }) {
-        println!(
-            "panic in start_on_this_thread_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
+        assert_eq!(
+            server.wlcs_display_server.version,
+            WLCS_DISPLAY_SERVER_VERSION
         );
-    }
+        // SAFETY: WLCS keeps the event loop alive while start_on_this_thread runs on this thread.
+        let event_loop = unsafe { EventLoop::from_ptr(event_loop) };
+        server.state = ServerState::Running;
+        let started = Instant::now();
+        server.wlcs.start_on_this_thread(event_loop);
+        let elapsed = started.elapsed();
+        if cfg!(debug_assertions) && elapsed < START_ON_THIS_THREAD_MIN_DURATION {
+            diagnostics::warn(format_args!(
+                "start_on_this_thread_ffi: start_on_this_thread returned after {:?}, is it implemented?",
+                elapsed
+            ));
+        }
+    })
 }
 
-const fn wlcs_display_server<W: Wlcs>() -> WlcsDisplayServer {
+/// The WlcsDisplayServer for `W`, with every entry point set.
+///
+/// The wrappers hand this to WLCS as part of each server they create; it is exposed to prototype partial
+/// integrations and inspect the FFI layout. Entries can be cleared by setting them to `None` on the server
+/// returned by the entry point of [`create_server_fn`], e.g. from a custom `create_server`, as the wrappers do
+/// for [`Wlcs::supports_pointer`]. WLCS requires `start`, `stop`, `create_client_socket` and
+/// `position_window_absolute`, and skips the tests needing any other entry left unset.
+///
+/// Building the table is safe, but its `unsafe` entry points may only be called with a pointer returned by the
+/// entry point of `create_server_fn::<W>()`, and not yet destroyed: they cast it back to the server of type `W`
+/// the table is embedded in. Calling them with any other `WlcsDisplayServer`, e.g. a copy of this table, is
+/// undefined behavior.
+pub const fn wlcs_display_server<W: Wlcs>() -> WlcsDisplayServer {
     WlcsDisplayServer {
-        version: 3,
+        version: WLCS_DISPLAY_SERVER_VERSION,
         start: Some(start_server_ffi::<W>),
         stop: Some(stop_server_ffi::<W>),
         create_client_socket: Some(create_client_socket_ffi::<W>),
@@ -317,38 +1034,215 @@ where
     W: Wlcs,
 {
     WlcsServerIntegration {
-        version: 1,
+        version: WLCS_SERVER_INTEGRATION_VERSION,
         create_server: Some(create_server_ffi::<W>),
         destroy_server: Some(destroy_server_ffi::<W>),
     }
 }
 
+/// Check at compile time that the structures handed to WLCS for `W` have the versions and layouts of the WLCS
+/// headers this crate targets.
+///
+/// The expected versions and layouts are written out from `wlcs/display_server.h` rather than taken from the
+/// bindings, so regenerating the bindings against other headers without updating the crate fails here. A mismatch
+/// makes WLCS reject the integration when loading it, or worse, call through the wrong entries, so
+/// [`crate::wlcs_server_integration!`] evaluates this in a constant, turning the mismatch into a compile error.
+pub const fn assert_abi_versions<W: Wlcs>() {
+    use std::mem::size_of;
+
+    use memoffset::offset_of;
+
+    // The version fields are padded to the alignment of the entry points which follow them.
+    let entry = size_of::<usize>();
+    assert!(
+        wlcs_server::<W>().version == 1,
+        "WlcsServerIntegration version does not match the WLCS headers this crate targets"
+    );
+    assert!(
+        size_of::<WlcsServerIntegration>() == 3 * entry
+            && offset_of!(WlcsServerIntegration, destroy_server) == 2 * entry,
+        "WlcsServerIntegration layout does not match the WLCS headers this crate targets"
+    );
+    assert!(
+        wlcs_display_server::<W>().version == 3,
+        "WlcsDisplayServer version does not match the WLCS headers this crate targets"
+    );
+    assert!(
+        size_of::<WlcsDisplayServer>() == 9 * entry
+            && offset_of!(WlcsDisplayServer, start_on_this_thread) == 8 * entry,
+        "WlcsDisplayServer layout does not match the WLCS headers this crate targets"
+    );
+}
+
+/// Signature of the `create_server` entry point of [`WlcsServerIntegration`].
+pub type CreateServerFn =
+    unsafe extern "C" fn(argc: c_int, argv: *mut *const c_char) -> *mut WlcsDisplayServer;
+
+/// The `create_server` entry point of the WlcsServerIntegration for `W`.
+///
+/// This should not be called directly. Instead the selector form of [`crate::wlcs_server_integration!`] should be
+/// used.
+pub const fn create_server_fn<W: Wlcs>() -> CreateServerFn {
+    create_server_ffi::<W>
+}
+
+/// Instantiate a WlcsServerIntegration creating servers through `create_server`, which must call one of the
+/// entry points returned by [`create_server_fn`].
+///
+/// This should not be called directly. Instead the selector form of [`crate::wlcs_server_integration!`] should be
+/// used.
+pub const fn wlcs_server_select(create_server: CreateServerFn) -> WlcsServerIntegration {
+    WlcsServerIntegration {
+        version: WLCS_SERVER_INTEGRATION_VERSION,
+        create_server: Some(create_server),
+        destroy_server: Some(destroy_selected_server_ffi),
+    }
+}
+
+/// Create a server through the entry point at the index returned by `selector` in `servers`.
+///
+/// This should not be called directly. Instead the selector form of [`crate::wlcs_server_integration!`] should be
+/// used.
+///
+/// # Safety
+///
+/// Same as calling the entry points in `servers` with `argc` and `argv`.
+pub unsafe fn select_server(
+    servers: &[CreateServerFn],
+    selector: fn() -> usize,
+    argc: c_int,
+    argv: *mut *const c_char,
+) -> *mut WlcsDisplayServer {
+    let selected = ffi_guard!(args(argc), None, {
+        let index = selector();
+        let selected = servers.get(index).copied();
+        if selected.is_none() {
+            diagnostics::error(format_args!(
+                "select_server: selected integration {} but only {} are available",
+                index,
+                servers.len()
+            ));
+        }
+        selected
+    });
+    match selected {
+        Some(create_server) => unsafe { create_server(argc, argv) },
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// The fields of every [`DisplayServerHandle`] which do not depend on its `W`.
+#[repr(C)]
+struct DisplayServerPrefix {
+    wlcs_display_server: WlcsDisplayServer,
+    destroy: unsafe extern "C" fn(*mut WlcsDisplayServer),
+}
+
+/// Destroy a server whose type was selected at runtime, by calling the `destroy_server_ffi` it was created with.
+unsafe extern "C" fn destroy_selected_server_ffi(ptr: *mut WlcsDisplayServer) {
+    // SAFETY: the server was created by create_server_ffi, as a repr(C) DisplayServerHandle starting with the
+    // same fields as DisplayServerPrefix.
+    let destroy = unsafe { (*ptr.cast::<DisplayServerPrefix>()).destroy };
+    unsafe { destroy(ptr) }
+}
+
 /// Instantiate the WlcsServerIntegration for the specified type.
 ///
+/// This defines the `wlcs_server_integration` static WLCS looks up in the integration module. It is only
+/// exported (`#[no_mangle]`) outside of `cfg(test)` builds of the invoking crate, so the unit tests of an
+/// integration can link it without symbol clashes. Pass `export = false` to never export it, e.g. in a crate
+/// that is only ever linked as a library into other integrations.
+///
+/// With the `init-function` feature, the exported static is accompanied by an exported
+/// `extern "C" fn wlcs_server_integration_init() -> *const WlcsServerIntegration` returning a pointer to it, for
+/// loaders probing a function symbol instead.
+///
+/// A single library can also present several integrations, e.g. compositor configurations, with
+/// `wlcs_server_integration!(select = selector, [Handle, OtherHandle])`. WLCS looks up one integration per
+/// module, so the selection happens each time WLCS creates a server: `selector` is a `fn() -> usize` returning
+/// the index of the type to create, typically from an environment variable. It should return the same index for
+/// the whole process, as process-wide settings such as [`set_sigpipe_handling`] are shared by all the types.
+///
+/// The integration crate itself is usually built as both a module for WLCS and a library for its tests:
+///
+/// ```toml
+/// [lib]
+/// crate-type = ["cdylib", "rlib"]
+/// ```
+///
 /// See [`Wlcs`] trait.
 #[macro_export]
 macro_rules! wlcs_server_integration {
     ($handle: ident) => {
-        #[no_mangle]
-        static wlcs_server_integration: WlcsServerIntegration = wlcs_server::<$handle>();
+        #[allow(dead_code, non_upper_case_globals)]
+        #[cfg_attr(not(test), no_mangle)]
+        static wlcs_server_integration: $crate::ffi_display_server_api::WlcsServerIntegration =
+            $crate::ffi_wrappers::wlcs_server::<$handle>();
+        const _: () = $crate::ffi_wrappers::assert_abi_versions::<$handle>();
+
+        $crate::__wlcs_server_integration_init!();
+    };
+    ($handle: ident, export = true) => {
+        $crate::wlcs_server_integration!($handle);
+    };
+    (select = $selector: expr, [$($handle: ident),+ $(,)?]) => {
+        #[allow(dead_code, non_upper_case_globals)]
+        #[cfg_attr(not(test), no_mangle)]
+        static wlcs_server_integration: $crate::ffi_display_server_api::WlcsServerIntegration =
+            $crate::ffi_wrappers::wlcs_server_select({
+                unsafe extern "C" fn create_server(
+                    argc: ::std::ffi::c_int,
+                    argv: *mut *const ::std::ffi::c_char,
+                ) -> *mut $crate::ffi_display_server_api::WlcsDisplayServer {
+                    const SERVERS: &[$crate::ffi_wrappers::CreateServerFn] =
+                        &[$($crate::ffi_wrappers::create_server_fn::<$handle>()),+];
+                    let selector: fn() -> usize = $selector;
+                    unsafe { $crate::ffi_wrappers::select_server(SERVERS, selector, argc, argv) }
+                }
+                create_server
+            });
+        $(const _: () = $crate::ffi_wrappers::assert_abi_versions::<$handle>();)+
+
+        $crate::__wlcs_server_integration_init!();
+    };
+    ($handle: ident, export = false) => {
+        #[allow(dead_code, non_upper_case_globals)]
+        static wlcs_server_integration: $crate::ffi_display_server_api::WlcsServerIntegration =
+            $crate::ffi_wrappers::wlcs_server::<$handle>();
+        const _: () = $crate::ffi_wrappers::assert_abi_versions::<$handle>();
+    };
+}
+
+#[cfg(feature = "init-function")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __wlcs_server_integration_init {
+    () => {
+        #[allow(dead_code)]
+        #[cfg_attr(not(test), no_mangle)]
+        extern "C" fn wlcs_server_integration_init(
+        ) -> *const $crate::ffi_display_server_api::WlcsServerIntegration {
+            &wlcs_server_integration
+        }
     };
 }
 
+#[cfg(not(feature = "init-function"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __wlcs_server_integration_init {
+    () => {};
+}
+
 unsafe extern "C" fn pointer_move_absolute_ffi<W: Wlcs>(
     ptr: *mut WlcsPointer,
     x: wl_fixed_t,
     y: wl_fixed_t,
 ) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(input, args(ptr, x, y), {
         let pointer = unsafe { get_pointer_handle::<W>(ptr) };
-        pointer.p.move_absolute(x, y);
-    }) {
-        println!(
-            "panic in pointer_move_absolute_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
-        );
-    }
+        pointer.p.move_absolute(Fixed(x), Fixed(y));
+    })
 }
 
 unsafe extern "C" fn pointer_move_relative_ffi<W: Wlcs>(
@@ -356,65 +1250,50 @@ unsafe extern "C" fn pointer_move_relative_ffi<W: Wlcs>(
     dx: wl_fixed_t,
     dy: wl_fixed_t,
 ) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(input, args(ptr, dx, dy), {
         let pointer = unsafe { get_pointer_handle::<W>(ptr) };
-        pointer.p.move_relative(dx, dy);
-    }) {
-        println!(
-            "panic in pointer_move_relative_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
-        );
-    }
+        pointer.p.move_relative_confined(Fixed(dx), Fixed(dy));
+    })
 }
 
 unsafe extern "C" fn pointer_button_up_ffi<W: Wlcs>(ptr: *mut WlcsPointer, button: i32) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(input, args(ptr, button), {
         let pointer = unsafe { get_pointer_handle::<W>(ptr) };
         pointer.p.button_up(button)
-    }) {
-        println!(
-            "panic in pointer_button_up_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
-        );
-    }
+    })
 }
 
 unsafe extern "C" fn pointer_button_down_ffi<W: Wlcs>(ptr: *mut WlcsPointer, button: i32) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(input, args(ptr, button), {
         let pointer = unsafe { get_pointer_handle::<W>(ptr) };
         pointer.p.button_down(button)
-    }) {
-        println!(
-            "panic in pointer_button_down_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
-        );
-    }
+    })
 }
 
 unsafe extern "C" fn pointer_destroy_ffi<W: Wlcs>(ptr: *mut WlcsPointer) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(args(ptr), {
         // SAFETY:
         // - wlcs will no longer use the WlcsPointer pointer. This ensures we take back ownership of the
         //   allocation.
         // - The PointerHandle was created using Box::from_raw, ensuring the memory layout is correct.
+        unsafe { debug_assert_pointer_version(ptr) };
         let mut pointer =
             unsafe { Box::from_raw(container_of!(ptr, PointerHandle<W>, wlcs_pointer)) };
-        pointer.p.destroy()
-    }) {
-        println!(
-            "panic in pointer_destroy_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
-        );
-    }
+        POINTERS_DESTROYED.fetch_add(1, Ordering::Relaxed);
+        unregister_device(DeviceKind::Pointer, ptr as usize);
+        // The box is owned again, so a panicking destroy still drops the pointer below.
+        if let Err(err) =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| pointer.p.destroy()))
+        {
+            diagnostics::report_panic(function_name!(), &*err);
+        }
+        drop(pointer);
+    })
 }
 
 const fn wlcs_pointer<W: Wlcs>() -> WlcsPointer {
     WlcsPointer {
-        version: 1,
+        version: WLCS_POINTER_VERSION,
         move_absolute: Some(pointer_move_absolute_ffi::<W>),
         move_relative: Some(pointer_move_relative_ffi::<W>),
         button_up: Some(pointer_button_up_ffi::<W>),
@@ -424,67 +1303,336 @@ const fn wlcs_pointer<W: Wlcs>() -> WlcsPointer {
 }
 
 unsafe extern "C" fn touch_down_ffi<W: Wlcs>(ptr: *mut WlcsTouch, x: wl_fixed_t, y: wl_fixed_t) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(input, args(ptr, x, y), {
         let touch = unsafe { get_touch_handle::<W>(ptr) };
-        touch.t.touch_down(x, y);
-    }) {
-        println!(
-            "panic in touch_down_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
-        );
-    }
+        touch.t.touch_down(Fixed(x), Fixed(y));
+    })
 }
 
 unsafe extern "C" fn touch_move_ffi<W: Wlcs>(ptr: *mut WlcsTouch, x: wl_fixed_t, y: wl_fixed_t) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(input, args(ptr, x, y), {
         let touch = unsafe { get_touch_handle::<W>(ptr) };
-        touch.t.touch_move(x, y);
-    }) {
-        println!(
-            "panic in touch_down_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
-        );
-    }
+        touch.t.touch_move(Fixed(x), Fixed(y));
+    })
 }
 
 unsafe extern "C" fn touch_up_ffi<W: Wlcs>(ptr: *mut WlcsTouch) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(input, args(ptr), {
         let touch = unsafe { get_touch_handle::<W>(ptr) };
         touch.t.touch_up();
-    }) {
-        println!(
-            "panic in touch_up_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
-        );
-    }
+    })
 }
 
 unsafe extern "C" fn touch_destroy_ffi<W: Wlcs>(ptr: *mut WlcsTouch) {
-    if let Err(err) = std::panic::catch_unwind(|| {
+    ffi_guard!(args(ptr), {
         // SAFETY:
         // - wlcs will no longer use the WlcsTouch pointer. This ensures we take back ownership of the
         //   allocation.
         // - The TouchHandle was created using Box::from_raw, ensuring the memory layout is correct.
+        unsafe { debug_assert_touch_version(ptr) };
         let mut touch = unsafe { Box::from_raw(container_of!(ptr, TouchHandle<W>, wlcs_touch)) };
-        touch.t.destroy()
-    }) {
-        println!(
-            "panic in touch_destroy_ffi on ptr: {:p} (type {:?})",
-            err.as_ref() as *const _,
-            err.type_id()
-        );
-    }
+        TOUCHES_DESTROYED.fetch_add(1, Ordering::Relaxed);
+        unregister_device(DeviceKind::Touch, ptr as usize);
+        // The box is owned again, so a panicking destroy still drops the touch below.
+        if let Err(err) =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| touch.t.destroy()))
+        {
+            diagnostics::report_panic(function_name!(), &*err);
+        }
+        drop(touch);
+    })
 }
 
 const fn wlcs_touch<W: Wlcs>() -> WlcsTouch {
     WlcsTouch {
-        version: 1,
+        version: WLCS_TOUCH_VERSION,
         touch_down: Some(touch_down_ffi::<W>),
         touch_move: Some(touch_move_ffi::<W>),
         touch_up: Some(touch_up_ffi::<W>),
         destroy: Some(touch_destroy_ffi::<W>),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::OwnedFd;
+
+    use super::*;
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct PanickingDevice;
+
+    impl Pointer for PanickingDevice {
+        fn move_absolute(&mut self, _x: Fixed, _y: Fixed) {}
+        fn move_relative(&mut self, _dx: Fixed, _dy: Fixed) {}
+        fn button_up(&mut self, _button: i32) {}
+        fn button_down(&mut self, _button: i32) {}
+        fn destroy(&mut self) {
+            panic!("destroy");
+        }
+    }
+
+    impl Touch for PanickingDevice {
+        fn touch_down(&mut self, _x: Fixed, _y: Fixed) {}
+        fn touch_move(&mut self, _x: Fixed, _y: Fixed) {}
+        fn touch_up(&mut self) {}
+        fn destroy(&mut self) {
+            panic!("destroy");
+        }
+    }
+
+    impl Drop for PanickingDevice {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    struct Server;
+
+    impl Wlcs for Server {
+        type Pointer = PanickingDevice;
+        type Touch = PanickingDevice;
+
+        fn new() -> Result<Self, WlcsError> {
+            Ok(Server)
+        }
+        fn start(&mut self) {}
+        fn stop(&mut self) -> Result<(), WlcsError> {
+            Ok(())
+        }
+        fn create_client_socket(&self) -> std::io::Result<OwnedFd> {
+            Err(std::io::ErrorKind::Unsupported.into())
+        }
+        fn position_window_absolute(
+            &self,
+            _display: *mut wl_display,
+            _surface: SurfaceProxy<'_>,
+            _x: i32,
+            _y: i32,
+        ) {
+        }
+        fn create_pointer(&mut self) -> Option<Self::Pointer> {
+            Some(PanickingDevice)
+        }
+        fn create_touch(&mut self) -> Option<Self::Touch> {
+            Some(PanickingDevice)
+        }
+        fn get_descriptor(&self) -> &WlcsIntegrationDescriptor {
+            static DESCRIPTOR: WlcsIntegrationDescriptor = WlcsIntegrationDescriptor::new(&[]);
+            &DESCRIPTOR
+        }
+    }
+
+    crate::wlcs_server_integration!(select = || 1, [Server, Server]);
+
+    #[test]
+    fn selected_server() {
+        let create_server = wlcs_server_integration.create_server.unwrap();
+        let destroy_server = wlcs_server_integration.destroy_server.unwrap();
+        unsafe {
+            let server = create_server(0, std::ptr::null_mut());
+            assert!(!server.is_null());
+            destroy_server(server);
+
+            let servers = [create_server_fn::<Server>()];
+            assert!(select_server(&servers, || 1, 0, std::ptr::null_mut()).is_null());
+        }
+    }
+
+    #[test]
+    fn panic_messages() {
+        let err = std::panic::catch_unwind(|| panic!("no DRM device")).unwrap_err();
+        assert_eq!(panic_message(&*err), Some("no DRM device"));
+        let err = std::panic::catch_unwind(|| panic!("no {} device", "DRM")).unwrap_err();
+        assert_eq!(panic_message(&*err), Some("no DRM device"));
+        let err = std::panic::catch_unwind(|| std::panic::panic_any(42)).unwrap_err();
+        assert_eq!(panic_message(&*err), None);
+    }
+
+    #[test]
+    fn function_name() {
+        fn touch_move_ffi() -> &'static str {
+            function_name!()
+        }
+
+        fn pointer_destroy_ffi() -> &'static str {
+            // Nested like the catch_unwind of a destroy inside the one of ffi_guard!
+            std::panic::catch_unwind(|| {
+                let destroy = || function_name!();
+                destroy()
+            })
+            .unwrap()
+        }
+
+        assert_eq!(touch_move_ffi(), "touch_move_ffi");
+        assert_eq!(pointer_destroy_ffi(), "pointer_destroy_ffi");
+        assert_eq!(function_name!(), "function_name");
+    }
+
+    #[test]
+    fn dangling_extension_names() {
+        static EXTENSIONS: &[WlcsExtensionDescriptor] = crate::extension_list!(("wl_seat", 7));
+        assert!(check_extension_pointers(&WlcsIntegrationDescriptor::new(EXTENSIONS)).is_ok());
+
+        let dangling = [WlcsExtensionDescriptor {
+            name: std::ptr::NonNull::dangling().as_ptr(),
+            version: 1,
+        }];
+        let descriptor = WlcsIntegrationDescriptor {
+            version: 1,
+            num_extensions: 1,
+            supported_extensions: dangling.as_ptr(),
+        };
+        assert!(check_extension_pointers(&descriptor).is_err());
+    }
+
+    #[cfg(feature = "white-box")]
+    #[test]
+    fn wlcs_ref_of_handle() {
+        let handle = Box::new(DisplayServerHandle {
+            wlcs_display_server: wlcs_display_server::<Server>(),
+            destroy: destroy_server_ffi::<Server>,
+            wlcs: Server,
+            state: ServerState::Created,
+            disabled: DisabledDevices::default(),
+        });
+        let wlcs = unsafe { wlcs_ref::<Server>(&handle.wlcs_display_server) };
+        assert!(std::ptr::eq(wlcs, &handle.wlcs));
+    }
+
+    #[test]
+    fn argv_to_args() {
+        let mut argv = [
+            b"wlcs\0".as_ptr().cast(),
+            std::ptr::null(),
+            b"--gtest_filter=*\0".as_ptr().cast(),
+        ];
+        let args = unsafe { args_from_argv(3, argv.as_mut_ptr()) };
+        assert_eq!(args, ["wlcs", "--gtest_filter=*"]);
+        assert!(unsafe { args_from_argv(0, std::ptr::null_mut()) }.is_empty());
+    }
+
+    #[test]
+    fn parse_disabled_devices() {
+        assert_eq!(DisabledDevices::parse(""), DisabledDevices::default());
+        assert_eq!(
+            DisabledDevices::parse("touch, keyboard"),
+            DisabledDevices {
+                pointer: false,
+                touch: true
+            }
+        );
+        assert_eq!(
+            DisabledDevices::parse("pointer,touch"),
+            DisabledDevices {
+                pointer: true,
+                touch: true
+            }
+        );
+    }
+
+    #[test]
+    fn panicking_destroy_drops_device() {
+        let pointer = Box::into_raw(Box::new(PointerHandle::<Server> {
+            wlcs_pointer: wlcs_pointer::<Server>(),
+            p: PanickingDevice,
+        }));
+        let touch = Box::into_raw(Box::new(TouchHandle::<Server> {
+            wlcs_touch: wlcs_touch::<Server>(),
+            t: PanickingDevice,
+        }));
+
+        unsafe {
+            pointer_destroy_ffi::<Server>(std::ptr::addr_of_mut!((*pointer).wlcs_pointer));
+            touch_destroy_ffi::<Server>(std::ptr::addr_of_mut!((*touch).wlcs_touch));
+        }
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn device_registry() {
+        // Addresses no other test hands out, as the registry is process wide
+        register_device(DeviceKind::Pointer, 1, 1);
+        register_device(DeviceKind::Touch, 1, 1);
+        let pointer = DeviceHandle {
+            kind: DeviceKind::Pointer,
+            address: 1,
+            server: 1,
+        };
+        let touch = DeviceHandle {
+            kind: DeviceKind::Touch,
+            address: 1,
+            server: 1,
+        };
+        assert_eq!(live_devices_of(1), (1, 1));
+        assert!(device_handles().contains(&pointer));
+        assert!(device_handles().contains(&touch));
+
+        unregister_device(DeviceKind::Pointer, 1);
+        assert!(!device_handles().contains(&pointer));
+        assert!(device_handles().contains(&touch));
+        unregister_device(DeviceKind::Touch, 1);
+        assert!(!device_handles().contains(&touch));
+    }
+
+    struct NoopServer;
+
+    impl Wlcs for NoopServer {
+        type Pointer = crate::devices::NoopPointer;
+        type Touch = crate::devices::NoopTouch;
+
+        fn new() -> Result<Self, WlcsError> {
+            Ok(NoopServer)
+        }
+        fn start(&mut self) {}
+        fn stop(&mut self) -> Result<(), WlcsError> {
+            Ok(())
+        }
+        fn create_client_socket(&self) -> std::io::Result<OwnedFd> {
+            Err(std::io::ErrorKind::Unsupported.into())
+        }
+        fn position_window_absolute(
+            &self,
+            _display: *mut wl_display,
+            _surface: SurfaceProxy<'_>,
+            _x: i32,
+            _y: i32,
+        ) {
+        }
+        fn create_pointer(&mut self) -> Option<Self::Pointer> {
+            Some(crate::devices::NoopPointer)
+        }
+        fn create_touch(&mut self) -> Option<Self::Touch> {
+            Some(crate::devices::NoopTouch)
+        }
+        fn get_descriptor(&self) -> &WlcsIntegrationDescriptor {
+            static EXTENSIONS: &[WlcsExtensionDescriptor] =
+                crate::extension_list!(("wl_compositor", 4));
+            static DESCRIPTOR: WlcsIntegrationDescriptor =
+                WlcsIntegrationDescriptor::new(EXTENSIONS);
+            &DESCRIPTOR
+        }
+    }
+
+    #[test]
+    fn devices_tracked_per_server() {
+        unsafe {
+            let first = create_server_ffi::<NoopServer>(0, std::ptr::null_mut());
+            let second = create_server_ffi::<NoopServer>(0, std::ptr::null_mut());
+            assert!(!first.is_null() && !second.is_null());
+            let pointer = create_pointer_ffi::<NoopServer>(second);
+            assert!(!pointer.is_null());
+
+            // The pointer of the second server is not a leak of the first one.
+            assert_eq!(live_devices_of(first as usize), (0, 0));
+            assert_eq!(live_devices_of(second as usize), (1, 0));
+            destroy_server_ffi::<NoopServer>(first);
+            assert_eq!(live_devices_of(second as usize), (1, 0));
+
+            pointer_destroy_ffi::<NoopServer>(pointer);
+            assert_eq!(live_devices_of(second as usize), (0, 0));
+            destroy_server_ffi::<NoopServer>(second);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//!
+//! Arithmetic on `wl_fixed_t` values
+//!
+//! `wl_fixed_t` is a signed 24.8 fixed-point number stored in an `i32`. Adding or subtracting fixed-point values
+//! directly is exact, so accumulating relative motion this way loses no precision, unlike round trips through
+//! `f64`. These helpers saturate at the `i32` range instead of overflowing.
+//!
+//! The [`Pointer`](crate::Pointer) and [`Touch`](crate::Touch) traits take coordinates as [`Fixed`], which keeps
+//! pixel integers from being passed where fixed-point is expected.
+//!
+
+use wayland_sys::common::{wl_fixed_t, wl_fixed_to_double};
+
+/// A `wl_fixed_t` fixed-point value, with a precision of 1/256.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed(pub wl_fixed_t);
+
+impl Fixed {
+    /// Zero.
+    pub const ZERO: Fixed = Fixed(0);
+
+    /// The fixed-point value of an integer, saturating at the bounds of `wl_fixed_t` for integers outside of
+    /// about ±2^23.
+    pub const fn from_int(value: i32) -> Self {
+        Fixed(value.saturating_mul(256))
+    }
+
+    /// The fixed-point value of an integer, or `None` if it is outside of the range of `wl_fixed_t`.
+    pub const fn checked_from_int(value: i32) -> Option<Self> {
+        match value.checked_mul(256) {
+            Some(value) => Some(Fixed(value)),
+            None => None,
+        }
+    }
+
+    /// The closest fixed-point value of `value`, saturating at the bounds of `wl_fixed_t`.
+    ///
+    /// Unlike `wl_fixed_from_double` of `wayland-sys`, which truncates towards zero, this rounds to the nearest
+    /// multiple of 1/256, halfway cases away from zero. NaN converts to zero.
+    pub fn from_f64(value: f64) -> Self {
+        Fixed((value * 256.0).round() as wl_fixed_t)
+    }
+
+    /// The value as `f64`, which is exact.
+    pub fn to_f64(self) -> f64 {
+        wl_fixed_to_double(self.0)
+    }
+
+    /// The integer part, rounding towards negative infinity.
+    pub const fn to_int(self) -> i32 {
+        self.0 >> 8
+    }
+
+    /// Add `other`, see [`fixed_add`].
+    pub const fn saturating_add(self, other: Fixed) -> Fixed {
+        Fixed(fixed_add(self.0, other.0))
+    }
+
+    /// Subtract `other`, see [`fixed_sub`].
+    pub const fn saturating_sub(self, other: Fixed) -> Fixed {
+        Fixed(fixed_sub(self.0, other.0))
+    }
+
+    /// Clamp to `min..=max`, see [`fixed_clamp`].
+    pub const fn clamp(self, min: Fixed, max: Fixed) -> Fixed {
+        Fixed(fixed_clamp(self.0, min.0, max.0))
+    }
+}
+
+impl From<f64> for Fixed {
+    fn from(value: f64) -> Self {
+        Fixed::from_f64(value)
+    }
+}
+
+impl From<Fixed> for f64 {
+    fn from(value: Fixed) -> Self {
+        value.to_f64()
+    }
+}
+
+/// Add two fixed-point values, saturating at the bounds of `wl_fixed_t`.
+pub const fn fixed_add(a: wl_fixed_t, b: wl_fixed_t) -> wl_fixed_t {
+    a.saturating_add(b)
+}
+
+/// Subtract `b` from `a`, saturating at the bounds of `wl_fixed_t`.
+pub const fn fixed_sub(a: wl_fixed_t, b: wl_fixed_t) -> wl_fixed_t {
+    a.saturating_sub(b)
+}
+
+/// Clamp a fixed-point value to `min..=max`, e.g. to keep an accumulated position on an output.
+///
+/// # Panics
+///
+/// Panics if `min > max`.
+pub const fn fixed_clamp(value: wl_fixed_t, min: wl_fixed_t, max: wl_fixed_t) -> wl_fixed_t {
+    assert!(min <= max, "fixed_clamp: min > max");
+    if value < min {
+        min
+    } else if value > max {
+        max
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wayland_sys::common::{wl_fixed_from_double, wl_fixed_from_int, wl_fixed_to_double};
+
+    use super::*;
+
+    #[test]
+    fn exact_accumulation() {
+        let step = wl_fixed_from_double(0.1);
+        let mut position = 0;
+        for _ in 0..1000 {
+            position = fixed_add(position, step);
+        }
+        assert_eq!(position, step * 1000);
+        assert_eq!(fixed_sub(position, step * 1000), 0);
+        assert!((wl_fixed_to_double(position) - 1000.0 * wl_fixed_to_double(step)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn saturates() {
+        assert_eq!(fixed_add(i32::MAX, 1), i32::MAX);
+        assert_eq!(fixed_add(i32::MIN, -1), i32::MIN);
+        assert_eq!(fixed_sub(i32::MIN, 1), i32::MIN);
+        assert_eq!(fixed_sub(i32::MAX, -1), i32::MAX);
+    }
+
+    #[test]
+    fn fixed_conversions() {
+        assert_eq!(Fixed::from_int(3), Fixed(768));
+        assert_eq!(Fixed::from(10.5), Fixed(2688));
+        assert_eq!(f64::from(Fixed(-128)), -0.5);
+        assert_eq!(Fixed(-128).to_int(), -1);
+        assert_eq!(
+            Fixed::from_int(5).saturating_sub(Fixed::from_int(7)),
+            Fixed::from_int(-2)
+        );
+    }
+
+    #[test]
+    fn rounds_to_closest() {
+        assert_eq!(Fixed::from_f64(0.0039), Fixed(1));
+        assert_eq!(Fixed::from_f64(-0.0039), Fixed(-1));
+        assert_eq!(Fixed::from_f64(1.999), Fixed(512));
+        assert_eq!(Fixed::from_f64(0.001), Fixed::ZERO);
+        assert_eq!(Fixed::from_f64(1e12), Fixed(i32::MAX));
+        assert_eq!(Fixed::from_f64(f64::NAN), Fixed::ZERO);
+    }
+
+    #[test]
+    fn int_bounds() {
+        let max = i32::MAX >> 8;
+        assert_eq!(Fixed::from_int(max), Fixed(max * 256));
+        assert_eq!(Fixed::from_int(i32::MAX), Fixed(i32::MAX));
+        assert_eq!(Fixed::from_int(i32::MIN), Fixed(i32::MIN));
+        assert_eq!(Fixed::checked_from_int(max), Some(Fixed(max * 256)));
+        assert_eq!(Fixed::checked_from_int(-3), Some(Fixed(-768)));
+        assert_eq!(Fixed::checked_from_int(max + 1), None);
+        assert_eq!(Fixed::checked_from_int(i32::MIN), None);
+    }
+
+    #[test]
+    fn clamp() {
+        let max = wl_fixed_from_int(1920);
+        assert_eq!(fixed_clamp(wl_fixed_from_int(-5), 0, max), 0);
+        assert_eq!(fixed_clamp(wl_fixed_from_int(2000), 0, max), max);
+        assert_eq!(fixed_clamp(256, 0, max), 256);
+    }
+}
@@ -0,0 +1,309 @@
+//!
+//! Coordinate types
+//!
+//! WLCS passes positions either in whole pixels ([`Point`], e.g. to
+//! [`Wlcs::position_window_absolute`](crate::Wlcs::position_window_absolute)) or in `Fixed` fixed-point
+//! ([`FixedPoint`], e.g. to [`Pointer::move_absolute`](crate::Pointer::move_absolute)). Both are in the global
+//! compositor space, with the origin at the top-left and y growing downwards. Naming the type makes the unit of
+//! each value obvious and avoids transposing x and y.
+//!
+//! Surface-local coordinates have their origin at the top-left corner of the surface, as used by
+//! `wl_pointer.motion` and `wl_touch.down`. The global position of a surface is the position WLCS gives to
+//! [`Wlcs::position_window_absolute`](crate::Wlcs::position_window_absolute); [`global_to_surface_local`]
+//! converts between both.
+//!
+
+use crate::fixed::Fixed;
+
+/// A position in whole pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Point {
+    /// x coordinate
+    pub x: i32,
+    /// y coordinate
+    pub y: i32,
+}
+
+impl Point {
+    /// Create a point.
+    pub const fn new(x: i32, y: i32) -> Self {
+        Point { x, y }
+    }
+}
+
+/// The largest absolute coordinate [`checked_position`] accepts, 16 times the `i16` range.
+///
+/// This leaves room for huge multi-output layouts, while keeping offsets added to a coordinate, or the
+/// coordinate converted to `Fixed`, far from overflowing.
+pub const MAX_SANE_COORDINATE: i32 = i16::MAX as i32 * 16;
+
+/// The point at `(x, y)`, or `None` if a coordinate is beyond [`MAX_SANE_COORDINATE`].
+///
+/// Coordinates from WLCS are plain `i32`s, and garbage from malformed test input overflows as soon as an
+/// integration adds an offset or casts it. The wrappers check the position given to
+/// [`Wlcs::position_window_absolute`](crate::Wlcs::position_window_absolute) with this and warn about absurd
+/// ones.
+pub const fn checked_position(x: i32, y: i32) -> Option<Point> {
+    if x.unsigned_abs() > MAX_SANE_COORDINATE as u32
+        || y.unsigned_abs() > MAX_SANE_COORDINATE as u32
+    {
+        None
+    } else {
+        Some(Point { x, y })
+    }
+}
+
+/// A position in `Fixed` fixed-point, with a precision of 1/256 pixel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FixedPoint {
+    /// x coordinate
+    pub x: Fixed,
+    /// y coordinate
+    pub y: Fixed,
+}
+
+impl FixedPoint {
+    /// Create a point from fixed-point coordinates.
+    pub const fn new(x: Fixed, y: Fixed) -> Self {
+        FixedPoint { x, y }
+    }
+
+    /// Create a point from coordinates in pixels.
+    pub fn from_f64(x: f64, y: f64) -> Self {
+        FixedPoint {
+            x: Fixed::from_f64(x),
+            y: Fixed::from_f64(y),
+        }
+    }
+
+    /// The coordinates in pixels.
+    pub fn to_f64(self) -> (f64, f64) {
+        (self.x.to_f64(), self.y.to_f64())
+    }
+
+    /// The pixel containing this position, rounding towards negative infinity.
+    pub const fn to_point(self) -> Point {
+        Point {
+            x: self.x.to_int(),
+            y: self.y.to_int(),
+        }
+    }
+}
+
+/// A rectangle in whole pixels, from its top-left corner.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Rectangle {
+    /// Position of the top-left corner
+    pub loc: Point,
+    /// Width
+    pub width: i32,
+    /// Height
+    pub height: i32,
+}
+
+impl Rectangle {
+    /// Create a rectangle.
+    pub const fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Rectangle {
+            loc: Point { x, y },
+            width,
+            height,
+        }
+    }
+
+    /// Whether `point` is inside the rectangle, the bottom and right edges being excluded.
+    pub const fn contains(&self, point: Point) -> bool {
+        // Both differences are non-negative, and saturating at `i32::MAX` keeps them beyond any width.
+        point.x >= self.loc.x
+            && point.y >= self.loc.y
+            && point.x.saturating_sub(self.loc.x) < self.width
+            && point.y.saturating_sub(self.loc.y) < self.height
+    }
+
+    /// Clamp `point` to the rectangle, the bottom and right edges being excluded.
+    ///
+    /// An empty rectangle clamps every point to its top-left corner. Edges beyond the range of `Fixed` are
+    /// saturated, see [`Fixed::from_int`].
+    pub const fn clamp(&self, point: FixedPoint) -> FixedPoint {
+        let (min_x, min_y) = (Fixed::from_int(self.loc.x), Fixed::from_int(self.loc.y));
+        let max_x = Fixed::from_int(self.loc.x.saturating_add(self.width)).saturating_sub(Fixed(1));
+        let max_y =
+            Fixed::from_int(self.loc.y.saturating_add(self.height)).saturating_sub(Fixed(1));
+        FixedPoint {
+            x: point
+                .x
+                .clamp(min_x, if max_x.0 < min_x.0 { min_x } else { max_x }),
+            y: point
+                .y
+                .clamp(min_y, if max_y.0 < min_y.0 { min_y } else { max_y }),
+        }
+    }
+
+    /// The point at `(nx, ny)` in coordinates normalized to the rectangle, `(0, 0)` being its top-left corner
+    /// and `(1, 1)` its bottom-right one, or `None` if a coordinate is outside of `0..=1`.
+    ///
+    /// The result is [clamped](Rectangle::clamp), so the bottom and right edges map to the last pixel inside.
+    pub fn denormalize(&self, nx: f64, ny: f64) -> Option<FixedPoint> {
+        if !(0.0..=1.0).contains(&nx) || !(0.0..=1.0).contains(&ny) {
+            return None;
+        }
+        Some(self.clamp(FixedPoint::from_f64(
+            self.loc.x as f64 + nx * self.width as f64,
+            self.loc.y as f64 + ny * self.height as f64,
+        )))
+    }
+}
+
+/// An output to add to the compositor, see [`Wlcs::add_output`](crate::Wlcs::add_output).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputInfo {
+    /// Area of the global compositor space covered by the output, in logical pixels
+    pub geometry: Rectangle,
+    /// Integer scale factor
+    pub scale: i32,
+    /// Refresh rate in mHz
+    pub refresh: i32,
+}
+
+impl OutputInfo {
+    /// An output covering `geometry`, with a scale of 1 and a refresh rate of 60Hz.
+    pub const fn new(geometry: Rectangle) -> Self {
+        OutputInfo {
+            geometry,
+            scale: 1,
+            refresh: 60_000,
+        }
+    }
+}
+
+/// Convert `global`, in the global compositor space, to coordinates local to the surface covering `surface_geo`.
+///
+/// This subtracts the position of the surface, so points left of or above the surface have negative
+/// coordinates. Whether `global` is actually on the surface can be checked with [`Rectangle::contains`].
+/// Coordinates saturate at the bounds of `i32`.
+pub const fn global_to_surface_local(surface_geo: Rectangle, global: Point) -> Point {
+    Point {
+        x: global.x.saturating_sub(surface_geo.loc.x),
+        y: global.y.saturating_sub(surface_geo.loc.y),
+    }
+}
+
+impl From<Point> for FixedPoint {
+    fn from(point: Point) -> Self {
+        FixedPoint {
+            x: Fixed::from_int(point.x),
+            y: Fixed::from_int(point.y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let point = Point::new(100, -50);
+        let fixed = FixedPoint::from(point);
+        assert_eq!(fixed, FixedPoint::new(Fixed(100 * 256), Fixed(-50 * 256)));
+        assert_eq!(fixed.to_point(), point);
+        assert_eq!(fixed.to_f64(), (100.0, -50.0));
+
+        assert_eq!(
+            FixedPoint::from_f64(10.5, -0.5).to_point(),
+            Point::new(10, -1)
+        );
+    }
+
+    #[test]
+    fn sane_positions() {
+        assert_eq!(checked_position(-200, 3840), Some(Point::new(-200, 3840)));
+        assert_eq!(
+            checked_position(MAX_SANE_COORDINATE, -MAX_SANE_COORDINATE),
+            Some(Point::new(MAX_SANE_COORDINATE, -MAX_SANE_COORDINATE))
+        );
+        assert_eq!(checked_position(i32::MIN, 0), None);
+        assert_eq!(checked_position(0, MAX_SANE_COORDINATE + 1), None);
+    }
+
+    #[test]
+    fn rectangle_contains() {
+        let rectangle = Rectangle::new(1920, 0, 1280, 720);
+        assert!(rectangle.contains(Point::new(1920, 0)));
+        assert!(rectangle.contains(Point::new(3199, 719)));
+        assert!(!rectangle.contains(Point::new(3200, 0)));
+        assert!(!rectangle.contains(Point::new(1919, 10)));
+    }
+
+    #[test]
+    fn rectangle_extremes() {
+        let huge = Rectangle::new(i32::MIN, i32::MIN, i32::MAX, i32::MAX);
+        assert!(huge.contains(Point::new(i32::MIN, i32::MIN)));
+        assert!(huge.contains(Point::new(-2, -2)));
+        assert!(!huge.contains(Point::new(-1, 0)));
+        assert!(!huge.contains(Point::new(i32::MAX, i32::MAX)));
+        assert!(Rectangle::new(i32::MAX, 0, i32::MAX, 1).contains(Point::new(i32::MAX, 0)));
+
+        assert_eq!(
+            huge.clamp(FixedPoint::from_f64(0.0, -2.0)),
+            FixedPoint::new(Fixed(-257), Fixed::from_int(-2))
+        );
+        assert_eq!(
+            Rectangle::new(i32::MAX - 10, i32::MIN, 100, 100)
+                .clamp(FixedPoint::new(Fixed(i32::MAX), Fixed(i32::MIN))),
+            FixedPoint::new(Fixed(i32::MAX), Fixed(i32::MIN))
+        );
+
+        assert_eq!(
+            global_to_surface_local(Rectangle::new(i32::MIN, i32::MAX, 1, 1), Point::new(1, -1)),
+            Point::new(i32::MAX, i32::MIN)
+        );
+    }
+
+    #[test]
+    fn rectangle_clamp() {
+        let rectangle = Rectangle::new(0, 0, 100, 50);
+        assert_eq!(
+            rectangle.clamp(FixedPoint::from_f64(-10.0, 20.5)),
+            FixedPoint::from_f64(0.0, 20.5)
+        );
+        assert_eq!(
+            rectangle
+                .clamp(FixedPoint::from_f64(150.0, 50.0))
+                .to_point(),
+            Point::new(99, 49)
+        );
+        assert_eq!(
+            Rectangle::new(10, 10, 0, 0).clamp(FixedPoint::from_f64(20.0, 0.0)),
+            FixedPoint::from_f64(10.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn rectangle_denormalize() {
+        let rectangle = Rectangle::new(100, 0, 200, 100);
+        assert_eq!(
+            rectangle.denormalize(0.5, 0.25),
+            Some(FixedPoint::from_f64(200.0, 25.0))
+        );
+        assert_eq!(
+            rectangle.denormalize(1.0, 1.0).map(FixedPoint::to_point),
+            Some(Point::new(299, 99))
+        );
+        assert_eq!(rectangle.denormalize(-0.1, 0.5), None);
+        assert_eq!(rectangle.denormalize(0.5, f64::NAN), None);
+    }
+
+    #[test]
+    fn surface_local() {
+        let surface = Rectangle::new(100, 200, 50, 50);
+        assert_eq!(
+            global_to_surface_local(surface, Point::new(110, 220)),
+            Point::new(10, 20)
+        );
+        assert_eq!(
+            global_to_surface_local(surface, Point::new(90, 200)),
+            Point::new(-10, 0)
+        );
+    }
+}
@@ -5,20 +5,41 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 #![warn(missing_docs)]
 
-use std::os::fd::OwnedFd;
-
-use wayland_sys::{
-    client::{wl_display, wl_proxy},
-    common::wl_fixed_t,
-    server::wl_event_loop,
+use std::{
+    error::Error,
+    ffi::OsString,
+    fmt,
+    marker::PhantomData,
+    os::fd::{BorrowedFd, OwnedFd, RawFd},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
-use crate::ffi_display_server_api::WlcsIntegrationDescriptor;
+use wayland_sys::client::{wl_display, wl_proxy};
+
+use crate::{
+    clock::ClockSource,
+    event_loop::EventLoop,
+    ffi_display_server_api::WlcsIntegrationDescriptor,
+    fixed::Fixed,
+    geometry::{FixedPoint, OutputInfo, Rectangle},
+};
 
+pub mod clock;
+pub mod devices;
+mod diagnostics;
+pub mod event_loop;
 pub mod ffi_display_server_api;
 pub mod ffi_pointer_api;
 pub mod ffi_touch_api;
 pub mod ffi_wrappers;
+pub mod fixed;
+pub mod geometry;
+pub mod presets;
+pub mod seat;
+pub mod socket;
+mod sys;
+pub mod testing;
 
 /// Build WLCS extension extension_list
 ///
@@ -27,6 +48,19 @@ pub mod ffi_wrappers;
 /// * `name` - The name of the Wayland protocol
 /// * `version` - Version number of the corresponding protocol
 ///
+/// Entries are checked at compile time: the list must not be empty, names must be string literals which are not
+/// empty, and versions must be constants greater than zero:
+///
+/// ```compile_fail
+/// # use wlcs::ffi_display_server_api::WlcsExtensionDescriptor;
+/// static EXTENSIONS: &[WlcsExtensionDescriptor] = wlcs::extension_list!(("wl_compositor", 0));
+/// ```
+///
+/// ```compile_fail
+/// # use wlcs::ffi_display_server_api::WlcsExtensionDescriptor;
+/// static EXTENSIONS: &[WlcsExtensionDescriptor] = wlcs::extension_list!(("", 1));
+/// ```
+///
 /// # Examples
 /// use wlcs_rs::extension_list
 ///
@@ -39,72 +73,639 @@ pub mod ffi_wrappers;
 ///     ("xdg_wm_base", 3),
 /// );
 ///
-/// static DESCRIPTOR: WlcsIntegrationDescriptor = WlcsIntegrationDescriptor {
-///     version: 1,
-///     num_extensions: SUPPORTED_EXTENSIONS.len(),
-///     supported_extensions: SUPPORTED_EXTENSIONS.as_ptr(),
-/// };
+/// static DESCRIPTOR: WlcsIntegrationDescriptor = WlcsIntegrationDescriptor::new(SUPPORTED_EXTENSIONS);
 #[macro_export]
 macro_rules! extension_list {
-    ($(($name: expr, $version: expr)),* $(,)?) => {
+    () => {
+        compile_error!("extension_list! needs at least one extension")
+    };
+    ($(($name: expr, $version: expr)),+ $(,)?) => {{
+        $(
+            const _: () = assert!(!$name.is_empty(), "extension_list! entry with an empty name");
+            const _: () = assert!(
+                $version > 0,
+                concat!("extension_list! entry ", $name, " has version 0")
+            );
+        )+
         &[$(
-            WlcsExtensionDescriptor {
+            $crate::ffi_display_server_api::WlcsExtensionDescriptor {
                 name: concat!($name, "\0").as_ptr() as *const std::os::raw::c_char,
                 version: $version
             }
-        ),*]
-    };
+        ),+]
+    }};
+}
+
+/// A client-side `wl_surface` proxy, as passed to [`Wlcs::position_window_absolute`].
+///
+/// # Invariant
+///
+/// The wrapped pointer refers to a live `wl_proxy` whose interface is `wl_surface`. The proxy belongs to a
+/// WLCS client connection and is only guaranteed to stay alive for the lifetime `'a`, which is the duration of
+/// the call it was passed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceProxy<'a> {
+    ptr: *mut wl_proxy,
+    _lifetime: PhantomData<&'a wl_proxy>,
+}
+
+impl<'a> SurfaceProxy<'a> {
+    /// Wrap a raw `wl_surface` proxy.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must refer to a `wl_proxy` of interface `wl_surface` which stays alive for `'a`.
+    pub unsafe fn from_ptr(ptr: *mut wl_proxy) -> Self {
+        SurfaceProxy {
+            ptr,
+            _lifetime: PhantomData,
+        }
+    }
+
+    /// The underlying `wl_proxy` pointer.
+    pub fn as_ptr(&self) -> *mut wl_proxy {
+        self.ptr
+    }
+
+    /// Protocol id of the surface, shared by the client proxy and the server-side object.
+    pub fn protocol_id(&self) -> u32 {
+        use wayland_sys::{client::*, ffi_dispatch};
+
+        // SAFETY: the proxy is alive for 'a, see the invariant.
+        unsafe { ffi_dispatch!(wayland_client_handle(), wl_proxy_get_id, self.ptr) }
+    }
+
+    /// Resolve the server-side `wl_surface` of this proxy, for integrations built on [`wayland_server`].
+    ///
+    /// The proxy is a client-side object of a WLCS client living in the same process as the compositor, not a
+    /// server-side resource, so it cannot be converted directly. Both sides share the protocol id of the object
+    /// though, which is looked up among the objects of `client`. `client` must be the compositor client WLCS
+    /// connected through the display passed along with the proxy, see
+    /// [`client_display_fd`](crate::socket::client_display_fd) to identify it.
+    #[cfg(feature = "wayland-server")]
+    pub fn server_surface(
+        &self,
+        client: &wayland_server::Client,
+        handle: &wayland_server::DisplayHandle,
+    ) -> Result<wayland_server::protocol::wl_surface::WlSurface, wayland_server::backend::InvalidId>
+    {
+        client.object_from_protocol_id(handle, self.protocol_id())
+    }
+
+    /// Convert the proxy to a [`wayland_backend`] client [`ObjectId`](wayland_backend::client::ObjectId).
+    ///
+    /// `interface` should be the `wl_surface` interface, e.g. `WlSurface::interface()` from `wayland-client`.
+    /// An error is returned if it does not match the interface of the proxy.
+    ///
+    /// # Safety
+    ///
+    /// The returned id must not be used after `'a` ends, as the proxy may be destroyed by WLCS afterwards.
+    #[cfg(feature = "wayland-backend")]
+    pub unsafe fn object_id(
+        &self,
+        interface: &'static wayland_backend::protocol::Interface,
+    ) -> Result<wayland_backend::client::ObjectId, wayland_backend::client::InvalidId> {
+        unsafe { wayland_backend::client::ObjectId::from_ptr(interface, self.ptr) }
+    }
+}
+
+/// Health of a running display server, see [`Wlcs::health`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Health {
+    /// The integration does not report its health.
+    #[default]
+    Unknown,
+    /// The server is fully functional.
+    Healthy,
+    /// The server runs, but some functionality is impaired, e.g. it fell back to software rendering.
+    Degraded,
+    /// The server cannot run tests, e.g. its device or thread is gone.
+    Unhealthy,
+}
+
+/// The process a display server is created in, see [`Wlcs::new_with_context`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerContext {
+    args: Vec<OsString>,
+    current_dir: Option<PathBuf>,
+    runtime_dir: Option<PathBuf>,
+}
+
+impl ServerContext {
+    /// Capture the context of the current process, with the arguments WLCS passed to the integration.
+    pub fn new(args: Vec<OsString>) -> Self {
+        ServerContext {
+            args,
+            current_dir: std::env::current_dir().ok(),
+            runtime_dir: std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from),
+        }
+    }
+
+    /// The command line arguments WLCS passed to `create_server`.
+    ///
+    /// These are the arguments of the WLCS test runner, starting with its own name, so they are empty when the
+    /// server is created by something other than WLCS.
+    pub fn args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    /// The working directory of the process, which is the directory WLCS was invoked from unless it changed it.
+    pub fn current_dir(&self) -> Option<&Path> {
+        self.current_dir.as_deref()
+    }
+
+    /// `XDG_RUNTIME_DIR`, where Wayland sockets and other runtime files are usually created.
+    ///
+    /// This is the raw value of the variable, see [`Wlcs::runtime_dir`] for a validated directory.
+    pub fn runtime_dir(&self) -> Option<&Path> {
+        self.runtime_dir.as_deref()
+    }
+}
+
+/// Error reported by a [`Wlcs`] integration.
+#[derive(Debug)]
+pub struct WlcsError {
+    inner: Box<dyn Error + Send + Sync>,
+}
+
+impl WlcsError {
+    /// Create an error from any error type or message.
+    pub fn new(error: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        WlcsError {
+            inner: error.into(),
+        }
+    }
+}
+
+impl fmt::Display for WlcsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl Error for WlcsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.inner.source()
+    }
+}
+
+impl From<std::io::Error> for WlcsError {
+    fn from(error: std::io::Error) -> Self {
+        WlcsError::new(error)
+    }
+}
+
+impl From<String> for WlcsError {
+    fn from(message: String) -> Self {
+        WlcsError::new(message)
+    }
+}
+
+impl From<&str> for WlcsError {
+    fn from(message: &str) -> Self {
+        WlcsError::new(message)
+    }
 }
 
 /// Trait to be implemented by Wlcs clients
-pub trait Wlcs {
+///
+/// # Call ordering
+///
+/// WLCS drives each server through the same lifecycle:
+///
+/// 1. [`Wlcs::new_with_context`], then [`Wlcs::configure`], when WLCS creates the server.
+/// 2. [`Wlcs::start`] or [`Wlcs::start_on_this_thread`], when a test sets up its fixture.
+/// 3. [`Wlcs::create_client_socket`], [`Wlcs::position_window_absolute`] and device creation through
+///    [`Wlcs::create_pointer`] and [`Wlcs::create_touch`], while the test runs. Devices are destroyed before
+///    the server is stopped.
+/// 4. [`Wlcs::stop`], when the test tears down its fixture, after which the server may be started again.
+/// 5. [`Wlcs::on_destroy`], right before the server is dropped.
+///
+/// [`Wlcs::get_descriptor`] may be called at any time. The wrappers track the state of the server, and warn
+/// when a device is requested before it was started or after it was stopped, see [`Wlcs::is_running`].
+pub trait Wlcs: Sized {
     /// The pointer type is what will be implemented and called by [`Wlcs::create_pointer`]
     type Pointer: Pointer;
 
     /// The touch type is what will be implemented and called by [`Wlcs::create_touch`]
     type Touch: Touch;
 
-    /// .
-    fn new() -> Self;
+    /// Create the display server.
+    ///
+    /// Returning an error, e.g. because a required device is missing, makes the server creation fail.
+    fn new() -> Result<Self, WlcsError>;
+
+    /// Create the display server, knowing the process it is created in.
+    ///
+    /// This is what the wrappers call, with the arguments WLCS passes to `create_server`, which lets integrations
+    /// locate configuration relative to the WLCS invocation. Defaults to [`Wlcs::new`], ignoring the context.
+    fn new_with_context(_context: &ServerContext) -> Result<Self, WlcsError> {
+        Self::new()
+    }
+
+    /// Name of the integration, e.g. of the compositor variant it drives.
+    ///
+    /// The wrappers log it the first time a server with this name is created, which tells apart integrations
+    /// loaded into the same process, or selected at runtime, in test logs. Defaults to `"wlcs-rs integration"`.
+    fn name(&self) -> &str {
+        "wlcs-rs integration"
+    }
+
+    /// Directory for the listening socket and other runtime files of the server, which
+    /// [`socket::bind_listener`] creates its socket in.
+    ///
+    /// WLCS connects its clients through [`Wlcs::create_client_socket`], so the wrappers never call this; it gives
+    /// integrations which also listen on a named socket one place to decide where it lives. Defaults to
+    /// [`socket::default_runtime_dir`], i.e. a validated `XDG_RUNTIME_DIR` with a fallback.
+    fn runtime_dir(&self) -> PathBuf {
+        socket::default_runtime_dir()
+    }
+
+    /// Configure the display server from its descriptor, after [`Wlcs::new`] and before [`Wlcs::start`].
+    ///
+    /// `descriptor` is a copy of the one returned by [`Wlcs::get_descriptor`]. WLCS does not negotiate extensions
+    /// nor tell which ones it will test: it fetches the descriptor whenever it needs it and skips the tests
+    /// requiring extensions missing from it. This hook lets the server create only the globals of the extensions
+    /// it advertises. The pointers of the copy go stale if the descriptor they point into is modified, so read it
+    /// first. Does nothing by default.
+    fn configure(&mut self, _descriptor: &WlcsIntegrationDescriptor) {}
 
     /// Start the display server
     fn start(&mut self);
 
     /// Stop the display server
-    fn stop(&mut self);
+    ///
+    /// WLCS calls this during test teardown. The server is stopped but not freed: WLCS may start it again, and
+    /// it is only freed once it is destroyed, see [`Wlcs::on_destroy`]. An error, e.g. because the server thread
+    /// could not be joined, is reported by the wrappers.
+    fn stop(&mut self) -> Result<(), WlcsError>;
+
+    /// Called when WLCS destroys the server, right before it is dropped.
+    ///
+    /// This is the place to deterministically join threads spawned by the integration, e.g. with
+    /// [`spawn_server_thread`](crate::ffi_wrappers::spawn_server_thread), which are still running because
+    /// [`Wlcs::stop`] was not called or did not join them. Does nothing by default.
+    fn on_destroy(&mut self) {}
+
+    /// Whether the server is running.
+    ///
+    /// The wrappers track [`Wlcs::start`] and [`Wlcs::stop`] themselves and warn when WLCS requests a device
+    /// while the server is stopped, which usually points to an ordering bug in test teardown. Override this if the
+    /// server can stop on its own, e.g. when its thread exits on an error. Defaults to `true`, deferring to the
+    /// tracking of the wrappers.
+    fn is_running(&self) -> bool {
+        true
+    }
+
+    /// Set the clock the timestamps of input events derive from.
+    ///
+    /// Integrations stamp the events of their devices with the time of `clock`, e.g. through
+    /// [`Pointer::set_time`], so that a test holding a [`ClockSource::Manual`] clock can advance time
+    /// deterministically and test timeouts without real sleeps. WLCS does not call this and has no notion of time,
+    /// so the wrappers never call it. Servers should use [`ClockSource::System`] until this is called. Does
+    /// nothing by default.
+    fn set_clock(&mut self, _clock: ClockSource) {}
+
+    /// Health of the server after [`Wlcs::start`], e.g. the status of its GPU or devices.
+    ///
+    /// WLCS does not use this, so the wrappers never call it; it is for custom runners and CI gating, which can
+    /// skip or fail a run on an unhealthy server instead of reporting spurious test failures. Defaults to
+    /// [`Health::Unknown`].
+    fn health(&self) -> Health {
+        Health::Unknown
+    }
+
+    /// Name of a listening socket (as in `WAYLAND_DISPLAY`) the integration additionally opens.
+    ///
+    /// WLCS does not use listening sockets: it connects its clients through [`Wlcs::create_client_socket`], so
+    /// tests never depend on this. An integration may still listen on a named socket, e.g. to attach
+    /// `WAYLAND_DEBUG` clients or other external tools to the test compositor. Returning the name here makes the
+    /// wrappers log it once the server has started. Defaults to `None`.
+    fn display_name(&self) -> Option<String> {
+        None
+    }
 
     /// Create a socket for a Wayland client.
+    ///
+    /// This is called once per client connection, possibly many times per server when tests reconnect clients.
+    /// Each call must register a new client with the compositor; see
+    /// [`ClientRegistry`](crate::socket::ClientRegistry) for tracking per-client state.
     fn create_client_socket(&self) -> std::io::Result<OwnedFd>;
 
+    /// Configure a client socket created by [`Wlcs::create_client_socket`] before it is handed to WLCS.
+    ///
+    /// Use this to set socket options like buffer sizes or `SO_PASSCRED` on every client socket. An error
+    /// fails the client connection, and the socket is closed. Defaults to doing nothing.
+    fn configure_client_socket(&self, fd: BorrowedFd<'_>) -> std::io::Result<()> {
+        let _ = fd;
+        Ok(())
+    }
+
+    /// Called by the wrappers right before the socket created by [`Wlcs::create_client_socket`] is handed to
+    /// WLCS, which connects a client to it right away.
+    ///
+    /// It runs once per successful `create_client_socket`, after [`Wlcs::configure_client_socket`], so a failed
+    /// connection is never reported. `fd` is owned by WLCS from then on, so it must not be used for I/O or closed,
+    /// but it identifies the connection: it is what [`client_display_fd`](crate::socket::client_display_fd)
+    /// returns for the display of the client. Together with [`Wlcs::on_client_disconnected`], it brackets the
+    /// lifetime of each client. Does nothing by default.
+    fn on_client_connected(&mut self, _fd: RawFd) {}
+
+    /// Counterpart of [`Wlcs::on_client_connected`] for when the client disconnects.
+    ///
+    /// WLCS does not report disconnections, and the end of the socket it owns cannot be watched without keeping
+    /// the connection alive, so the wrappers never call this. Integrations learn about disconnections through
+    /// their own end of the socket: the compositor reports them to
+    /// [`ClientRegistry::disconnect`](crate::socket::ClientRegistry::disconnect), or
+    /// [`ClientRegistry::disconnect_all`](crate::socket::ClientRegistry::disconnect_all) when it stops, which
+    /// hands the state registered in `create_client_socket` to the disconnect callback. Keeping the `fd` of
+    /// `on_client_connected` in that state and calling this with it from the callback, e.g. through the server
+    /// thread, makes the order of client teardown observable, such as clients still connected when
+    /// [`Wlcs::stop`] runs. `fd` may have been closed and reused by then. Does nothing by default.
+    fn on_client_disconnected(&mut self, _fd: RawFd) {}
+
     /// Position a window in absolute coordinates
     fn position_window_absolute(
         &self,
         display: *mut wl_display,
-        surface: *mut wl_proxy,
+        surface: SurfaceProxy<'_>,
         x: i32,
         y: i32,
     );
 
+    /// Add an output covering `output.geometry`, returning an id for [`Wlcs::remove_output`].
+    ///
+    /// Outputs live in the global compositor space, the same space [`Wlcs::position_window_absolute`] and the
+    /// absolute device positions are expressed in: a window positioned inside the geometry of an output shows up
+    /// on it, and removing the output may move windows or leave them off-screen. WLCS does not drive outputs, so
+    /// the wrappers never call this; it gives custom harnesses a uniform way to drive output hotplug. Returns
+    /// `None`, meaning unsupported, by default.
+    fn add_output(&mut self, _output: OutputInfo) -> Option<u32> {
+        None
+    }
+
+    /// Remove an output added with [`Wlcs::add_output`]. Does nothing by default.
+    fn remove_output(&mut self, _id: u32) {}
+
+    /// Whether the integration provides pointers at all.
+    ///
+    /// The WLCS descriptor only lists protocol extensions, so device support cannot be advertised there. Instead,
+    /// when this returns `false`, the wrappers leave `create_pointer` unset in the display server handed to WLCS,
+    /// which is how the WLCS ABI expresses a server without the device, rather than failing every creation.
+    /// Defaults to `true`.
+    fn supports_pointer(&self) -> bool {
+        true
+    }
+
+    /// Whether the integration provides touch devices at all, see [`Wlcs::supports_pointer`]. Defaults to `true`.
+    fn supports_touch(&self) -> bool {
+        true
+    }
+
     /// Create a wl_pointer
+    ///
+    /// Devices which need to feed events into the server can hold a [`WeakSeat`](crate::seat::WeakSeat) of a
+    /// [`SharedSeat`](crate::seat::SharedSeat) owned by the server.
+    ///
+    /// Resources shared by all devices, like the runtime of an async integration, belong to the server: create
+    /// them once in [`Wlcs::new`] or [`Wlcs::start`], and hand each device a clone of a cheap handle to them,
+    /// e.g. a `tokio::runtime::Handle` to spawn onto, rather than creating them per device. The devices are
+    /// called from the WLCS thread, so the handle must let them schedule work without blocking on the server.
     fn create_pointer(&mut self) -> Option<Self::Pointer>;
 
     /// Create a wl_touch
+    ///
+    /// See [`Wlcs::create_pointer`] for sharing state with the server.
     fn create_touch(&mut self) -> Option<Self::Touch>;
 
+    /// Create a wl_pointer on seat number `seat`.
+    ///
+    /// The WLCS interface has no notion of seats, so the wrappers always create devices on seat 0. Integrations
+    /// supporting multiple seats can override this to route devices, and call it with other seats from custom
+    /// test code. Defaults to [`Wlcs::create_pointer`] for seat 0 and `None` for any other seat.
+    fn create_pointer_on_seat(&mut self, seat: usize) -> Option<Self::Pointer> {
+        if seat == 0 {
+            self.create_pointer()
+        } else {
+            None
+        }
+    }
+
+    /// Create a wl_touch on seat number `seat`.
+    ///
+    /// Like [`Wlcs::create_pointer_on_seat`], the wrappers always use seat 0. Defaults to [`Wlcs::create_touch`]
+    /// for seat 0 and `None` for any other seat.
+    fn create_touch_on_seat(&mut self, seat: usize) -> Option<Self::Touch> {
+        if seat == 0 {
+            self.create_touch()
+        } else {
+            None
+        }
+    }
+
     /// Get the Integration descriptor
+    ///
+    /// WLCS keeps the returned pointer and reads through it later, so the descriptor must stay at the same
+    /// address for as long as WLCS may use it: return a `static`, or a
+    /// [`PinnedDescriptor`](crate::ffi_display_server_api::PinnedDescriptor) rather than a descriptor stored
+    /// inline in `self`. The descriptor may be computed from runtime state, see
+    /// [`DynamicDescriptor`](crate::ffi_display_server_api::DynamicDescriptor).
     fn get_descriptor(&self) -> &WlcsIntegrationDescriptor;
 
-    /// Option current thread startup
-    fn start_on_this_thread(&self, _event_loop: *mut wl_event_loop) {}
+    /// Optionally start the display server on the current thread, driving WLCS' event loop.
+    ///
+    /// The event loop is owned by WLCS and only borrowed for the duration of this call. WLCS considers the server
+    /// running once this is called, so it must run the server until it is stopped rather than return right away;
+    /// in debug builds, the wrappers warn when it returns within a few milliseconds. Does nothing by default.
+    fn start_on_this_thread(&self, _event_loop: EventLoop<'_>) {}
 }
 
 /// Trait for Wlcs clients implementing Pointer testing
 pub trait Pointer {
     /// Absolute pointer movement event
-    fn move_absolute(&mut self, x: wl_fixed_t, y: wl_fixed_t);
+    ///
+    /// This is a warp: the pointer jumps to `(x, y)` in global coordinates, as WLCS calls it to place the pointer
+    /// over a surface (`Pointer::move_to` in the WLCS test suite). It is not an interpolated motion, so
+    /// integrations should deliver a single motion to the new position, without intermediate events that tests
+    /// do not expect. WLCS has no other absolute movement, so there is no separate warp entry point.
+    fn move_absolute(&mut self, x: Fixed, y: Fixed);
 
     /// Relative pointer movement event
-    fn move_relative(&mut self, dx: wl_fixed_t, dy: wl_fixed_t);
+    ///
+    /// This is a stream of motion, like that of a mouse, moving the pointer by `(dx, dy)` from its current
+    /// position (`Pointer::move_by` in the WLCS test suite).
+    fn move_relative(&mut self, dx: Fixed, dy: Fixed);
+
+    /// Absolute pointer movement to pixel coordinates, converted to fixed-point for
+    /// [`Pointer::move_absolute`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wlcs::{fixed::Fixed, Pointer};
+    ///
+    /// #[derive(Default)]
+    /// struct Cursor {
+    ///     x: Fixed,
+    ///     y: Fixed,
+    /// }
+    ///
+    /// impl Pointer for Cursor {
+    ///     fn move_absolute(&mut self, x: Fixed, y: Fixed) {
+    ///         (self.x, self.y) = (x, y);
+    ///     }
+    ///     fn move_relative(&mut self, dx: Fixed, dy: Fixed) {
+    ///         (self.x, self.y) = (self.x.saturating_add(dx), self.y.saturating_add(dy));
+    ///     }
+    ///     fn button_up(&mut self, _button: i32) {}
+    ///     fn button_down(&mut self, _button: i32) {}
+    /// }
+    ///
+    /// let mut cursor = Cursor::default();
+    /// // Move to the middle of the pixel at (100, 50)
+    /// cursor.move_absolute_f64(100.5, 50.5);
+    /// assert_eq!((cursor.x, cursor.y), (Fixed(100 * 256 + 128), Fixed(50 * 256 + 128)));
+    /// ```
+    fn move_absolute_f64(&mut self, x: f64, y: f64) {
+        self.move_absolute(Fixed::from_f64(x), Fixed::from_f64(y))
+    }
+
+    /// Absolute pointer movement to `point`, see [`Pointer::move_absolute`].
+    fn move_absolute_point(&mut self, point: FixedPoint) {
+        self.move_absolute(point.x, point.y)
+    }
+
+    /// Confine the pointer to `region`, e.g. the outputs of the compositor.
+    ///
+    /// Integrations that model cursor confinement store the region and return it from [`Pointer::bounds`]; the
+    /// motion then stays within it when going through [`Pointer::move_absolute_clamped`]. Does nothing by
+    /// default, leaving the pointer unbounded.
+    fn set_bounds(&mut self, _region: Rectangle) {}
+
+    /// The region set by [`Pointer::set_bounds`], or `None` (the default) if the pointer is unbounded.
+    fn bounds(&self) -> Option<Rectangle> {
+        None
+    }
+
+    /// Absolute pointer movement clamped to [`Pointer::bounds`] before being forwarded to
+    /// [`Pointer::move_absolute`].
+    fn move_absolute_clamped(&mut self, x: Fixed, y: Fixed) {
+        let point = FixedPoint::new(x, y);
+        let point = match self.bounds() {
+            Some(bounds) => bounds.clamp(point),
+            None => point,
+        };
+        self.move_absolute_point(point)
+    }
+
+    /// Confine the pointer to `region`, as `zwp_pointer_constraints_v1.confine_pointer` does, or lift the
+    /// confinement with `None`.
+    ///
+    /// Integrations that model confinement store the region and return it from [`Pointer::confine_region`]; the
+    /// relative motion WLCS sends then goes through [`Pointer::move_relative_confined`], which clamps it. Does
+    /// nothing by default, leaving the pointer unconfined.
+    fn set_confine_region(&mut self, _region: Option<Rectangle>) {}
+
+    /// The region set by [`Pointer::set_confine_region`], or `None` (the default) if the pointer is unconfined.
+    fn confine_region(&self) -> Option<Rectangle> {
+        None
+    }
+
+    /// Relative pointer movement clamped to [`Pointer::confine_region`], which is how the wrappers forward the
+    /// relative motion of WLCS.
+    ///
+    /// The pointer moves by `(dx, dy)` from [`Pointer::position`], clamped to the region, and the motion is
+    /// forwarded to [`Pointer::move_relative_with_unaccel`]: clamped as the accelerated deltas, and unclamped as
+    /// the unaccelerated deltas, since `zwp_relative_pointer_v1` reports motion regardless of confinement. Without
+    /// a region or a known position, the motion is forwarded unclamped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wlcs::{fixed::Fixed, geometry::{FixedPoint, Rectangle}, Pointer};
+    ///
+    /// #[derive(Default)]
+    /// struct Cursor {
+    ///     position: FixedPoint,
+    ///     region: Option<Rectangle>,
+    /// }
+    ///
+    /// impl Pointer for Cursor {
+    ///     fn move_absolute(&mut self, x: Fixed, y: Fixed) {
+    ///         self.position = FixedPoint::new(x, y);
+    ///     }
+    ///     fn move_relative(&mut self, dx: Fixed, dy: Fixed) {
+    ///         let position = self.position;
+    ///         self.position = FixedPoint::new(position.x.saturating_add(dx), position.y.saturating_add(dy));
+    ///     }
+    ///     fn button_up(&mut self, _button: i32) {}
+    ///     fn button_down(&mut self, _button: i32) {}
+    ///     fn position(&self) -> Option<FixedPoint> {
+    ///         Some(self.position)
+    ///     }
+    ///     fn set_confine_region(&mut self, region: Option<Rectangle>) {
+    ///         self.region = region;
+    ///     }
+    ///     fn confine_region(&self) -> Option<Rectangle> {
+    ///         self.region
+    ///     }
+    /// }
+    ///
+    /// let mut cursor = Cursor::default();
+    /// cursor.set_confine_region(Some(Rectangle::new(0, 0, 100, 100)));
+    /// cursor.move_relative_confined(Fixed::from_int(150), Fixed::from_int(50));
+    /// assert_eq!(cursor.position, FixedPoint::new(Fixed(100 * 256 - 1), Fixed::from_int(50)));
+    /// ```
+    fn move_relative_confined(&mut self, dx: Fixed, dy: Fixed) {
+        let (confined_dx, confined_dy) = match (self.confine_region(), self.position()) {
+            (Some(region), Some(position)) => {
+                let target = region.clamp(FixedPoint::new(
+                    position.x.saturating_add(dx),
+                    position.y.saturating_add(dy),
+                ));
+                (
+                    target.x.saturating_sub(position.x),
+                    target.y.saturating_sub(position.y),
+                )
+            }
+            _ => (dx, dy),
+        };
+        self.move_relative_with_unaccel(confined_dx, confined_dy, dx, dy)
+    }
+
+    /// Relative pointer movement event with separate unaccelerated deltas, as reported by
+    /// `zwp_relative_pointer_v1.relative_motion`.
+    ///
+    /// WLCS only provides one set of deltas, so the wrappers call this, through
+    /// [`Pointer::move_relative_confined`], with the unaccelerated deltas equal to the accelerated ones unless the
+    /// pointer is confined. Defaults to [`Pointer::move_relative`], ignoring the unaccelerated deltas.
+    fn move_relative_with_unaccel(
+        &mut self,
+        dx: Fixed,
+        dy: Fixed,
+        _dx_unaccel: Fixed,
+        _dy_unaccel: Fixed,
+    ) {
+        self.move_relative(dx, dy)
+    }
+
+    /// Relative pointer movement event stamped with the time of the device event, as reported by
+    /// `zwp_relative_pointer_v1.relative_motion`.
+    ///
+    /// `utime` is a timestamp in microseconds with an undefined base, which the protocol splits into `utime_hi`
+    /// and `utime_lo`; only differences between timestamps are meaningful. The WLCS pointer interface has no
+    /// timestamps, so the wrappers never call this. It is available to custom test code driving timing-sensitive
+    /// relative motion. Defaults to [`Pointer::move_relative_with_unaccel`], ignoring the timestamp.
+    fn relative_motion_timed(
+        &mut self,
+        _utime: u64,
+        dx: Fixed,
+        dy: Fixed,
+        dx_unaccel: Fixed,
+        dy_unaccel: Fixed,
+    ) {
+        self.move_relative_with_unaccel(dx, dy, dx_unaccel, dy_unaccel)
+    }
 
     /// Release of button
     fn button_up(&mut self, button: i32);
@@ -112,21 +713,428 @@ pub trait Pointer {
     /// Press of button
     fn button_down(&mut self, button: i32);
 
+    /// Click `button` while holding the modifier keys `mods` down on `keyboard`.
+    ///
+    /// The modifiers are pressed in order, then the button is pressed and released, then the modifiers are
+    /// released in reverse order, so the compositor sees the modifiers held for the whole click. WLCS has no
+    /// keyboard, so pointers are not linked to one: the keyboard of the same seat is passed in by the caller,
+    /// and the wrappers never call this. The modifiers are Linux input key codes, see [`Keyboard::key_down`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::os::fd::OwnedFd;
+    /// use wlcs::{fixed::Fixed, Keyboard, Pointer};
+    ///
+    /// #[derive(Default)]
+    /// struct Log(Vec<String>);
+    ///
+    /// impl Pointer for Log {
+    ///     fn move_absolute(&mut self, _x: Fixed, _y: Fixed) {}
+    ///     fn move_relative(&mut self, _dx: Fixed, _dy: Fixed) {}
+    ///     fn button_up(&mut self, button: i32) {
+    ///         self.0.push(format!("button up {:#x}", button));
+    ///     }
+    ///     fn button_down(&mut self, button: i32) {
+    ///         self.0.push(format!("button down {:#x}", button));
+    ///     }
+    /// }
+    ///
+    /// impl Keyboard for Log {
+    ///     fn set_keymap(&mut self, _format: u32, _fd: OwnedFd, _size: u32) {}
+    ///     fn key_down(&mut self, key: u32) {
+    ///         self.0.push(format!("key down {}", key));
+    ///     }
+    ///     fn key_up(&mut self, key: u32) {
+    ///         self.0.push(format!("key up {}", key));
+    ///     }
+    /// }
+    ///
+    /// let (mut pointer, mut keyboard) = (Log::default(), Log::default());
+    /// // Ctrl+Shift+click
+    /// pointer.click_with_modifiers(&mut keyboard, 0x110, &[29, 42]);
+    /// assert_eq!(pointer.0, ["button down 0x110", "button up 0x110"]);
+    /// assert_eq!(keyboard.0, ["key down 29", "key down 42", "key up 42", "key up 29"]);
+    /// ```
+    fn click_with_modifiers(&mut self, keyboard: &mut dyn Keyboard, button: i32, mods: &[u32]) {
+        for &key in mods {
+            keyboard.key_down(key);
+        }
+        self.button_down(button);
+        self.button_up(button);
+        for &key in mods.iter().rev() {
+            keyboard.key_up(key);
+        }
+    }
+
+    /// Serial of the last `wl_pointer.button` event sent in response to [`Pointer::button_down`] or
+    /// [`Pointer::button_up`].
+    ///
+    /// WLCS does not pass serials through its pointer interface, so integrations that want to correlate grabs
+    /// with the exact press need to record the serial they used and report it here. Returns `None` by default.
+    fn last_serial(&self) -> Option<u32> {
+        None
+    }
+
+    /// Position of the cursor as tracked by the compositor, in global coordinates.
+    ///
+    /// WLCS tracks where it expects the pointer to be and checks it through what clients observe, so it never asks
+    /// for this. Integrations that track the position can expose it here for self-tests asserting that motion was
+    /// applied. Returns `None` by default.
+    fn position(&self) -> Option<FixedPoint> {
+        None
+    }
+
+    /// Source of the following scroll events, as reported by `wl_pointer.axis_source` (version 5).
+    ///
+    /// `source` is one of the `AXIS_SOURCE_*` constants in [`ffi_pointer_api`]: wheel, finger, continuous or
+    /// wheel tilt. The WLCS pointer interface (version 1) has no scroll entries, so the wrappers never call this.
+    /// It is available to integrations driving their devices from custom test code. Does nothing by default.
+    fn axis_source(&mut self, _source: u32) {}
+
+    /// End of scrolling on `axis`, as reported by `wl_pointer.axis_stop` (version 5).
+    ///
+    /// `axis` is [`AXIS_VERTICAL_SCROLL`](ffi_pointer_api::AXIS_VERTICAL_SCROLL) or
+    /// [`AXIS_HORIZONTAL_SCROLL`](ffi_pointer_api::AXIS_HORIZONTAL_SCROLL). Only sent for the finger source.
+    /// Like [`Pointer::axis_source`], this is never called by the wrappers. Does nothing by default.
+    fn axis_stop(&mut self, _axis: u32) {}
+
+    /// Set the timestamp, in milliseconds, of the events following this call.
+    ///
+    /// Compositors stamp `wl_pointer` events with the time of the input event, which clients use e.g. for
+    /// double-click intervals. WLCS does not pass timestamps through its pointer interface, so the wrappers never
+    /// call this and integrations pick timestamps themselves; custom test code can call it to control them.
+    /// Does nothing by default.
+    fn set_time(&mut self, _time_msec: u32) {}
+
+    /// Called by the wrappers right after creation with the version of the `WlcsPointer` interface handed to
+    /// WLCS.
+    ///
+    /// WLCS does not report which version it supports; it checks the version of the struct the integration
+    /// provides, which is [`WLCS_POINTER_VERSION`](ffi_pointer_api::WLCS_POINTER_VERSION) for this crate. The
+    /// version determines which entry points WLCS may call, so devices can adapt to what will be driven through
+    /// it. Does nothing by default.
+    fn set_interface_version(&mut self, _version: u32) {}
+
+    /// Merge relative motion received within `interval` into single events, to mimic the rate of real input
+    /// devices.
+    ///
+    /// A zero interval turns coalescing off, which is the default: every event is delivered. WLCS does not call
+    /// this; tests call it on devices wrapped in [`CoalescingPointer`](crate::devices::CoalescingPointer), which
+    /// implements it. Does nothing by default.
+    fn set_coalesce_interval(&mut self, _interval: Duration) {}
+
+    /// Apply a sequence of events recorded by `f` back to back.
+    ///
+    /// The events are only recorded while `f` runs, and applied through the primitive methods once it returns,
+    /// so the work done by `f` never interleaves with them. Integrations that dispatch input from another thread
+    /// can override this to hold their lock, or delay their flush, across [`BatchedPointer::apply`], so the
+    /// compositor never observes a half-applied sequence. WLCS drives pointers one event at a time and never
+    /// calls this; it is meant for custom test code.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wlcs::{ffi_pointer_api::BTN_LEFT, fixed::Fixed, Pointer};
+    ///
+    /// #[derive(Default)]
+    /// struct Log(Vec<String>);
+    ///
+    /// impl Pointer for Log {
+    ///     fn move_absolute(&mut self, x: Fixed, y: Fixed) {
+    ///         self.0.push(format!("move {} {}", x.to_int(), y.to_int()));
+    ///     }
+    ///     fn move_relative(&mut self, _dx: Fixed, _dy: Fixed) {}
+    ///     fn button_up(&mut self, _button: i32) {
+    ///         self.0.push("up".into());
+    ///     }
+    ///     fn button_down(&mut self, _button: i32) {
+    ///         self.0.push("down".into());
+    ///     }
+    /// }
+    ///
+    /// let mut log = Log::default();
+    /// log.batch(|drag| {
+    ///     drag.button_down(BTN_LEFT);
+    ///     drag.move_absolute_f64(20.0, 10.0);
+    ///     drag.button_up(BTN_LEFT);
+    /// });
+    /// assert_eq!(log.0, ["down", "move 20 10", "up"]);
+    /// ```
+    fn batch(&mut self, f: impl FnOnce(&mut BatchedPointer))
+    where
+        Self: Sized,
+    {
+        let mut batch = BatchedPointer::default();
+        f(&mut batch);
+        batch.apply(self);
+    }
+
+    /// Unplug the pointer in the middle of a test, to check how the compositor and its clients handle losing a
+    /// device.
+    ///
+    /// Unlike [`Pointer::destroy`], which releases the pointer once WLCS is done with it, this simulates a live
+    /// hotplug removal, and integrations supporting it should do what their compositor does when a real device
+    /// goes away: release the buttons the pointer holds, send `wl_pointer.leave` to the surface it focuses, and
+    /// remove the pointer capability from `wl_seat.capabilities` if no other pointer is left on the seat, which
+    /// tells clients to release their `wl_pointer` objects. Events after the removal should be ignored.
+    ///
+    /// WLCS has no notion of hotplug, so the wrappers never call this; custom harnesses do, and the wrappers still
+    /// call `destroy` on a removed pointer. Does nothing by default.
+    fn remove(&mut self) {}
+
     /// Destroy the pointer handle.
+    ///
+    /// The pointer is dropped right afterwards, even if this panics.
     fn destroy(&mut self) {}
 }
 
+/// Pointer events recorded by [`Pointer::batch`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BatchedPointer {
+    events: Vec<BatchedPointerEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BatchedPointerEvent {
+    MoveAbsolute(Fixed, Fixed),
+    MoveRelative(Fixed, Fixed),
+    ButtonDown(i32),
+    ButtonUp(i32),
+}
+
+impl BatchedPointer {
+    /// Record an absolute movement, see [`Pointer::move_absolute`].
+    pub fn move_absolute(&mut self, x: Fixed, y: Fixed) {
+        self.events.push(BatchedPointerEvent::MoveAbsolute(x, y));
+    }
+
+    /// Record an absolute movement to pixel coordinates, see [`Pointer::move_absolute_f64`].
+    pub fn move_absolute_f64(&mut self, x: f64, y: f64) {
+        self.move_absolute(Fixed::from_f64(x), Fixed::from_f64(y))
+    }
+
+    /// Record an absolute movement to `point`, see [`Pointer::move_absolute_point`].
+    pub fn move_absolute_point(&mut self, point: FixedPoint) {
+        self.move_absolute(point.x, point.y)
+    }
+
+    /// Record a relative movement, see [`Pointer::move_relative`].
+    pub fn move_relative(&mut self, dx: Fixed, dy: Fixed) {
+        self.events.push(BatchedPointerEvent::MoveRelative(dx, dy));
+    }
+
+    /// Record a button press, see [`Pointer::button_down`].
+    pub fn button_down(&mut self, button: i32) {
+        self.events.push(BatchedPointerEvent::ButtonDown(button));
+    }
+
+    /// Record a button release, see [`Pointer::button_up`].
+    pub fn button_up(&mut self, button: i32) {
+        self.events.push(BatchedPointerEvent::ButtonUp(button));
+    }
+
+    /// Number of recorded events.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether no events were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Apply the recorded events to `pointer`, in order.
+    pub fn apply<P: Pointer + ?Sized>(self, pointer: &mut P) {
+        for event in self.events {
+            match event {
+                BatchedPointerEvent::MoveAbsolute(x, y) => pointer.move_absolute(x, y),
+                BatchedPointerEvent::MoveRelative(dx, dy) => pointer.move_relative(dx, dy),
+                BatchedPointerEvent::ButtonDown(button) => pointer.button_down(button),
+                BatchedPointerEvent::ButtonUp(button) => pointer.button_up(button),
+            }
+        }
+    }
+}
+
 /// Trait for Wlcs clients implementing Pointer testing
 pub trait Touch {
     /// Start of a touch event
-    fn touch_down(&mut self, x: wl_fixed_t, y: wl_fixed_t);
+    fn touch_down(&mut self, x: Fixed, y: Fixed);
+
+    /// Start of a touch event at pixel coordinates, converted to fixed-point for [`Touch::touch_down`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wlcs::{fixed::Fixed, Touch};
+    ///
+    /// #[derive(Default)]
+    /// struct Finger {
+    ///     down_at: Option<(Fixed, Fixed)>,
+    /// }
+    ///
+    /// impl Touch for Finger {
+    ///     fn touch_down(&mut self, x: Fixed, y: Fixed) {
+    ///         self.down_at = Some((x, y));
+    ///     }
+    ///     fn touch_move(&mut self, _x: Fixed, _y: Fixed) {}
+    ///     fn touch_up(&mut self) {
+    ///         self.down_at = None;
+    ///     }
+    /// }
+    ///
+    /// let mut finger = Finger::default();
+    /// // Touch the top-left corner of the pixel at (20, 30)
+    /// finger.touch_down_f64(20.0, 30.0);
+    /// assert_eq!(finger.down_at, Some((Fixed::from_int(20), Fixed::from_int(30))));
+    /// ```
+    fn touch_down_f64(&mut self, x: f64, y: f64) {
+        self.touch_down(Fixed::from_f64(x), Fixed::from_f64(y))
+    }
+
+    /// Start of a touch event at `point`, see [`Touch::touch_down`].
+    fn touch_down_point(&mut self, point: FixedPoint) {
+        self.touch_down(point.x, point.y)
+    }
+
+    /// Start of a touch event at `(nx, ny)` in coordinates normalized to [`Touch::output_geometry`], from
+    /// `(0, 0)` at its top-left corner to `(1, 1)` at its bottom-right one.
+    ///
+    /// This lets tests touch e.g. the center of the output whatever its resolution. Fails without calling
+    /// [`Touch::touch_down`] if the integration does not provide the output geometry, or if a coordinate is
+    /// outside of `0..=1`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wlcs::{fixed::Fixed, geometry::Rectangle, Touch};
+    ///
+    /// #[derive(Default)]
+    /// struct Finger {
+    ///     down_at: Option<(Fixed, Fixed)>,
+    /// }
+    ///
+    /// impl Touch for Finger {
+    ///     fn touch_down(&mut self, x: Fixed, y: Fixed) {
+    ///         self.down_at = Some((x, y));
+    ///     }
+    ///     fn touch_move(&mut self, _x: Fixed, _y: Fixed) {}
+    ///     fn touch_up(&mut self) {
+    ///         self.down_at = None;
+    ///     }
+    ///     fn output_geometry(&self) -> Option<Rectangle> {
+    ///         Some(Rectangle::new(0, 0, 1920, 1080))
+    ///     }
+    /// }
+    ///
+    /// let mut finger = Finger::default();
+    /// finger.touch_down_normalized(0.5, 0.5).unwrap();
+    /// assert_eq!(finger.down_at, Some((Fixed::from_int(960), Fixed::from_int(540))));
+    /// assert!(finger.touch_down_normalized(1.5, 0.5).is_err());
+    /// ```
+    fn touch_down_normalized(&mut self, nx: f64, ny: f64) -> Result<(), WlcsError> {
+        let point = normalized_touch_point(self.output_geometry(), nx, ny)?;
+        self.touch_down_point(point);
+        Ok(())
+    }
 
     /// A "drag" event
-    fn touch_move(&mut self, x: wl_fixed_t, y: wl_fixed_t);
+    fn touch_move(&mut self, x: Fixed, y: Fixed);
+
+    /// A "drag" event to normalized coordinates, see [`Touch::touch_down_normalized`].
+    fn touch_move_normalized(&mut self, nx: f64, ny: f64) -> Result<(), WlcsError> {
+        let point = normalized_touch_point(self.output_geometry(), nx, ny)?;
+        self.touch_move_point(point);
+        Ok(())
+    }
+
+    /// A "drag" event to `point`, see [`Touch::touch_move`].
+    fn touch_move_point(&mut self, point: FixedPoint) {
+        self.touch_move(point.x, point.y)
+    }
 
     /// Event that bookends touch_down
     fn touch_up(&mut self);
 
+    /// End of a group of touch events which belong together, as marked by `wl_touch.frame`.
+    ///
+    /// Implementations batching events should hold the downs, moves and ups of all touch points until this is
+    /// called, then deliver them atomically. The WLCS touch interface (version 1) has no frame entry, so the
+    /// wrappers never call this; WLCS expects every event to be delivered immediately. Does nothing by default.
+    fn frame(&mut self) {}
+
+    /// Shape of the contact area of touch point `id`, as reported by `wl_touch.shape` (version 6), with the
+    /// lengths of the major and minor axes in surface coordinates.
+    ///
+    /// The WLCS touch interface (version 1) has neither touch ids nor contact shapes, so the wrappers never call
+    /// this. It is available to integrations driving their devices from custom test code. Does nothing by
+    /// default.
+    fn shape(&mut self, _id: i32, _major: Fixed, _minor: Fixed) {}
+
+    /// Orientation of the contact area of touch point `id` in degrees, as reported by `wl_touch.orientation`
+    /// (version 6).
+    ///
+    /// Like [`Touch::shape`], this is never called by the wrappers. Does nothing by default.
+    fn orientation(&mut self, _id: i32, _orientation: Fixed) {}
+
+    /// Geometry of the output the touch device is mapped to, in the global compositor space, which
+    /// [`Touch::touch_down_normalized`] converts against.
+    ///
+    /// WLCS passes absolute coordinates and never needs this. Defaults to `None`, for integrations which do not
+    /// expose their outputs.
+    fn output_geometry(&self) -> Option<Rectangle> {
+        None
+    }
+
+    /// Called by the wrappers right after creation with the version of the `WlcsTouch` interface handed to WLCS,
+    /// [`WLCS_TOUCH_VERSION`](ffi_touch_api::WLCS_TOUCH_VERSION) for this crate.
+    ///
+    /// See [`Pointer::set_interface_version`]. Does nothing by default.
+    fn set_interface_version(&mut self, _version: u32) {}
+
     /// Destroy a touch handle
+    ///
+    /// The touch is dropped right afterwards, even if this panics.
+    fn destroy(&mut self) {}
+}
+
+fn normalized_touch_point(
+    output: Option<Rectangle>,
+    nx: f64,
+    ny: f64,
+) -> Result<FixedPoint, WlcsError> {
+    let output = output
+        .ok_or("normalized touch coordinates require the output geometry of the touch device")?;
+    output.denormalize(nx, ny).ok_or_else(|| {
+        WlcsError::new(format!(
+            "normalized touch coordinates ({}, {}) are outside of 0..=1",
+            nx, ny
+        ))
+    })
+}
+
+/// The `wl_keyboard.keymap_format` value for an XKB keymap, see [`Keyboard::set_keymap`].
+pub const KEYMAP_FORMAT_XKB_V1: u32 = 1;
+
+/// Trait for keyboards of an integration
+///
+/// WLCS has no keyboard device, so the wrappers never create keyboards nor call this trait. It lets integrations
+/// and custom harnesses share a uniform interface for keyboard input, e.g. to test non-default layouts.
+pub trait Keyboard {
+    /// Forward a keymap to the compositor, mirroring `wl_keyboard.keymap`.
+    ///
+    /// `fd` is a file of `size` bytes holding the keymap in `format`, e.g. [`KEYMAP_FORMAT_XKB_V1`].
+    /// Ownership of the fd passes to the keyboard, which closes it by dropping it once the keymap was read or
+    /// re-sent to the clients; as clients map it, it should be sealed or otherwise never modified.
+    fn set_keymap(&mut self, format: u32, fd: OwnedFd, size: u32);
+
+    /// Press of `key`, a Linux input key code such as 29 for `KEY_LEFTCTRL`.
+    fn key_down(&mut self, key: u32);
+
+    /// Release of `key`
+    fn key_up(&mut self, key: u32);
+
+    /// Destroy a keyboard handle
     fn destroy(&mut self) {}
 }
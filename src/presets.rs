@@ -0,0 +1,33 @@
+//!
+//! Ready-made extension lists for common compositors
+//!
+
+use crate::{
+    extension_list,
+    ffi_display_server_api::{WlcsExtensionDescriptor, WlcsIntegrationDescriptor},
+};
+
+/// The core protocols most compositors support.
+///
+/// | Extension          | Version |
+/// |--------------------|---------|
+/// | `wl_compositor`    | 4       |
+/// | `wl_subcompositor` | 1       |
+/// | `wl_seat`          | 7       |
+/// | `wl_output`        | 4       |
+/// | `xdg_wm_base`      | 3       |
+///
+/// These are conservative versions widely implemented by compositors, not the latest ones. To advertise more,
+/// start a new [`extension_list!`] from these entries.
+pub const CORE_EXTENSIONS: &[WlcsExtensionDescriptor] = extension_list!(
+    ("wl_compositor", 4),
+    ("wl_subcompositor", 1),
+    ("wl_seat", 7),
+    ("wl_output", 4),
+    ("xdg_wm_base", 3),
+);
+
+/// A descriptor advertising [`CORE_EXTENSIONS`].
+pub const fn core_descriptor() -> WlcsIntegrationDescriptor {
+    WlcsIntegrationDescriptor::new(CORE_EXTENSIONS)
+}
@@ -0,0 +1,179 @@
+//!
+//! Sharing state between a display server and its input devices
+//!
+//! The devices returned by [`Wlcs::create_pointer`](crate::Wlcs::create_pointer) and
+//! [`Wlcs::create_touch`](crate::Wlcs::create_touch) are separate objects, called by WLCS on its own thread, while
+//! the compositor usually runs on a server thread. [`SharedSeat`] is a queue both sides hold: devices push input
+//! events into it, and the compositor drains them from its event loop after being woken up.
+//!
+//! Devices should hold a [`WeakSeat`], so a device which outlives the server does not keep its state alive.
+//!
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, Mutex, MutexGuard, Weak},
+};
+
+type Waker = Box<dyn Fn() + Send + Sync>;
+
+struct Inner<E> {
+    events: Mutex<VecDeque<E>>,
+    waker: Mutex<Option<Waker>>,
+}
+
+/// A queue of input events shared between a display server and its devices.
+///
+/// # Examples
+///
+/// ```
+/// use wlcs::{fixed::Fixed, seat::{SharedSeat, WeakSeat}, Pointer};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Input {
+///     Motion(Fixed, Fixed),
+///     Button(i32, bool),
+/// }
+///
+/// struct SeatPointer(WeakSeat<Input>);
+///
+/// impl Pointer for SeatPointer {
+///     fn move_absolute(&mut self, x: Fixed, y: Fixed) {
+///         self.0.push(Input::Motion(x, y));
+///     }
+///     fn move_relative(&mut self, _dx: Fixed, _dy: Fixed) {}
+///     fn button_up(&mut self, button: i32) {
+///         self.0.push(Input::Button(button, false));
+///     }
+///     fn button_down(&mut self, button: i32) {
+///         self.0.push(Input::Button(button, true));
+///     }
+/// }
+///
+/// // Held by the server, which hands out weak references in create_pointer
+/// let seat = SharedSeat::new();
+/// let mut pointer = SeatPointer(seat.downgrade());
+///
+/// pointer.move_absolute(Fixed::from_int(1), Fixed::from_int(1));
+/// pointer.button_down(0x110);
+/// assert_eq!(
+///     seat.drain(),
+///     [Input::Motion(Fixed(256), Fixed(256)), Input::Button(0x110, true)]
+/// );
+/// ```
+pub struct SharedSeat<E> {
+    inner: Arc<Inner<E>>,
+}
+
+impl<E> SharedSeat<E> {
+    /// Create an empty seat.
+    pub fn new() -> Self {
+        SharedSeat {
+            inner: Arc::new(Inner {
+                events: Mutex::new(VecDeque::new()),
+                waker: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Set a callback run after every pushed event, e.g. to wake up the compositor's event loop.
+    ///
+    /// The callback runs on the thread pushing the event, usually the WLCS thread.
+    pub fn set_waker(&self, waker: impl Fn() + Send + Sync + 'static) {
+        *lock(&self.inner.waker) = Some(Box::new(waker));
+    }
+
+    /// Queue an event and wake up the server.
+    pub fn push(&self, event: E) {
+        self.inner.push(event);
+    }
+
+    /// Take all queued events, oldest first.
+    pub fn drain(&self) -> Vec<E> {
+        lock(&self.inner.events).drain(..).collect()
+    }
+
+    /// A weak reference to the seat, to be held by devices.
+    pub fn downgrade(&self) -> WeakSeat<E> {
+        WeakSeat {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+}
+
+impl<E> Inner<E> {
+    fn push(&self, event: E) {
+        lock(&self.events).push_back(event);
+        if let Some(waker) = &*lock(&self.waker) {
+            waker();
+        }
+    }
+}
+
+impl<E> Clone for SharedSeat<E> {
+    fn clone(&self) -> Self {
+        SharedSeat {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<E> Default for SharedSeat<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> fmt::Debug for SharedSeat<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedSeat")
+            .field("queued", &lock(&self.inner.events).len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A weak reference to a [`SharedSeat`], see [`SharedSeat::downgrade`].
+pub struct WeakSeat<E> {
+    inner: Weak<Inner<E>>,
+}
+
+impl<E> WeakSeat<E> {
+    /// Queue an event if the seat is still alive.
+    ///
+    /// Returns `false` if the seat was dropped, in which case the event is discarded.
+    pub fn push(&self, event: E) -> bool {
+        match self.inner.upgrade() {
+            Some(inner) => {
+                inner.push(event);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the seat back, if it is still alive.
+    pub fn upgrade(&self) -> Option<SharedSeat<E>> {
+        self.inner.upgrade().map(|inner| SharedSeat { inner })
+    }
+}
+
+impl<E> Clone for WeakSeat<E> {
+    fn clone(&self) -> Self {
+        WeakSeat {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<E> fmt::Debug for WeakSeat<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WeakSeat").finish_non_exhaustive()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panic while queueing cannot leave the queue inconsistent.
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,394 @@
+//!
+//! Helpers for the client sockets handed to WLCS
+//!
+//! WLCS calls [`Wlcs::create_client_socket`](crate::Wlcs::create_client_socket) once per client connection, and
+//! tests which disconnect and reconnect clients call it many times over the lifetime of a server. Each call must
+//! register a new client with the compositor, and per-client state must be released when the client goes away,
+//! or it leaks across tests. [`ClientRegistry`] helps with the bookkeeping, and in debug builds
+//! [`check_fd_balance`] reports the sockets of [`create_socketpair`] which were never closed.
+//!
+//! Compositors also listening on a named socket, e.g. for clients spawned outside of WLCS, create it in the
+//! [`Wlcs::runtime_dir`](crate::Wlcs::runtime_dir) with [`bind_listener`].
+//!
+
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fmt, io,
+    os::{
+        fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd},
+        unix::net::UnixListener,
+    },
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use wayland_sys::client::wl_display;
+
+/// The client socket a WLCS client `display` is connected through.
+///
+/// WLCS connects each client to the fd returned by [`Wlcs::create_client_socket`](crate::Wlcs::create_client_socket),
+/// so this is that fd, and identifies which compositor client the display belongs to, e.g. in
+/// [`Wlcs::position_window_absolute`](crate::Wlcs::position_window_absolute). Record the raw fd of the client end
+/// along with the server-side client before returning it from `create_client_socket` to look it up later.
+///
+/// # Safety
+///
+/// `display` must be a live client `wl_display`.
+pub unsafe fn client_display_fd(display: *mut wl_display) -> RawFd {
+    use wayland_sys::{client::*, ffi_dispatch};
+
+    unsafe { ffi_dispatch!(wayland_client_handle(), wl_display_get_fd, display) }
+}
+
+/// Type of a Unix socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SocketType {
+    /// `SOCK_STREAM`
+    Stream,
+    /// `SOCK_SEQPACKET`
+    SeqPacket,
+}
+
+/// The type of the client sockets WLCS expects from [`Wlcs::create_client_socket`](crate::Wlcs::create_client_socket):
+/// [`SocketType::Stream`].
+///
+/// WLCS connects its clients with `wl_display_connect_to_fd`, and libwayland handles the connection as a byte
+/// stream on both ends, freely splitting and coalescing messages. With `SOCK_SEQPACKET`, reads stop at the
+/// boundaries of the writes of the peer, and data beyond the read buffer of a packet is discarded, which breaks
+/// the protocol in ways that only show up under load.
+pub const fn recommended_socket_type() -> SocketType {
+    SocketType::Stream
+}
+
+/// The directory for Wayland sockets and other runtime files, the default of
+/// [`Wlcs::runtime_dir`](crate::Wlcs::runtime_dir).
+///
+/// This is `XDG_RUNTIME_DIR` when it is an absolute path to an existing directory. Otherwise, e.g. on CI runners
+/// without a login session, it warns and falls back to [`std::env::temp_dir`].
+pub fn default_runtime_dir() -> PathBuf {
+    match runtime_dir_from(std::env::var_os("XDG_RUNTIME_DIR")) {
+        Ok(dir) => dir,
+        Err(reason) => {
+            let fallback = std::env::temp_dir();
+            crate::diagnostics::warn(format_args!(
+                "XDG_RUNTIME_DIR {}, using {} instead",
+                reason,
+                fallback.display()
+            ));
+            fallback
+        }
+    }
+}
+
+fn runtime_dir_from(var: Option<OsString>) -> Result<PathBuf, &'static str> {
+    let dir = PathBuf::from(var.ok_or("is not set")?);
+    if !dir.is_absolute() {
+        return Err("is not an absolute path");
+    }
+    if !dir.is_dir() {
+        return Err("is not a directory");
+    }
+    Ok(dir)
+}
+
+/// Bind a close-on-exec Unix socket named `name` in the [`Wlcs::runtime_dir`](crate::Wlcs::runtime_dir) of
+/// `wlcs`, e.g. the `wayland-N` socket clients find through `WAYLAND_DISPLAY`.
+///
+/// Fails if `name` is not a plain file name, or if the socket already exists, as another server may be
+/// listening on it.
+pub fn bind_listener<W: crate::Wlcs>(wlcs: &W, name: &str) -> io::Result<UnixListener> {
+    bind_listener_in(&wlcs.runtime_dir(), name)
+}
+
+fn bind_listener_in(runtime_dir: &Path, name: &str) -> io::Result<UnixListener> {
+    let mut components = Path::new(name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(std::path::Component::Normal(_)), None)
+    ) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("socket name {:?} is not a file name", name),
+        ));
+    }
+    UnixListener::bind(runtime_dir.join(name))
+}
+
+/// Create a connected pair of close-on-exec Unix sockets of the [`recommended_socket_type`].
+///
+/// The first socket is for the compositor, the second one is returned to WLCS from
+/// [`Wlcs::create_client_socket`](crate::Wlcs::create_client_socket).
+pub fn create_socketpair() -> io::Result<(OwnedFd, OwnedFd)> {
+    let (server, client) = crate::sys::socketpair(recommended_socket_type())?;
+    if cfg!(debug_assertions) {
+        let mut tracked = tracked_sockets();
+        tracked.extend(TrackedSocket::new(server.as_fd()));
+        tracked.extend(TrackedSocket::new(client.as_fd()));
+    }
+    Ok((server, client))
+}
+
+/// A socket created by [`create_socketpair`], identified by its inode as its fd may be reused once closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TrackedSocket {
+    fd: RawFd,
+    inode: (u64, u64),
+}
+
+impl TrackedSocket {
+    fn new(fd: BorrowedFd<'_>) -> Option<Self> {
+        let fd = fd.as_raw_fd();
+        crate::sys::fd_inode(fd).map(|inode| TrackedSocket { fd, inode })
+    }
+
+    fn is_open(&self) -> bool {
+        crate::sys::fd_inode(self.fd) == Some(self.inode)
+    }
+}
+
+static TRACKED_SOCKETS: Mutex<Vec<TrackedSocket>> = Mutex::new(Vec::new());
+
+fn tracked_sockets() -> std::sync::MutexGuard<'static, Vec<TrackedSocket>> {
+    TRACKED_SOCKETS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Forget the closed sockets of `tracked`, returning how many are still open.
+fn prune_closed(tracked: &mut Vec<TrackedSocket>) -> usize {
+    tracked.retain(TrackedSocket::is_open);
+    tracked.len()
+}
+
+/// Warn about sockets created by [`create_socketpair`] which are still open, returning their number.
+///
+/// The wrappers call this when WLCS destroys a server: by then, WLCS closed the client ends of its clients, and
+/// the compositor should have closed the server ends once the clients disconnected, so any socket still open
+/// leaks. It checks all the sockets of the process, so it reports false positives while several servers are
+/// alive; the wrappers only call it when the last live server is destroyed. Sockets are only tracked in debug
+/// builds, so this always returns 0 in release builds.
+pub fn check_fd_balance() -> usize {
+    let open = prune_closed(&mut tracked_sockets());
+    if open != 0 {
+        crate::diagnostics::warn(format_args!(
+            "check_fd_balance: {} sockets from create_socketpair were never closed",
+            open
+        ));
+    }
+    open
+}
+
+/// Credentials of the process on the other end of a Unix socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCred {
+    /// Process id of the peer
+    pub pid: i32,
+    /// User id of the peer
+    pub uid: u32,
+    /// Group id of the peer
+    pub gid: u32,
+}
+
+/// Query the credentials of the peer of a connected Unix socket, using `SO_PEERCRED`.
+///
+/// This can be used from [`Wlcs::create_client_socket`](crate::Wlcs::create_client_socket) on the server end
+/// of the socket pair. The credentials are those of the process which connected the socket (or created the
+/// pair), so with WLCS clients they are the ones of the WLCS process itself.
+pub fn peer_cred(fd: BorrowedFd<'_>) -> io::Result<PeerCred> {
+    crate::sys::peer_cred(fd)
+}
+
+/// Identifier of a client registered in a [`ClientRegistry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientId(u64);
+
+/// Per-client state of the clients created through [`Wlcs::create_client_socket`](crate::Wlcs::create_client_socket).
+///
+/// Register a client with its state when creating its socket, and report its disconnection from the
+/// compositor's client-destroyed hook (e.g. `ClientData::disconnected` with `wayland-server`). The state is then
+/// removed and handed to the disconnect callback for cleanup.
+///
+/// # Examples
+///
+/// ```
+/// use std::os::{fd::OwnedFd, unix::net::UnixStream};
+/// use wlcs::socket::ClientRegistry;
+///
+/// let registry = ClientRegistry::new(|id, name: String| println!("{:?} ({}) disconnected", id, name));
+///
+/// // In create_client_socket
+/// let (server, client) = UnixStream::pair().unwrap();
+/// let id = registry.register("first client".to_owned());
+/// // ... hand `server` and `id` to the compositor, return `client` to WLCS
+/// let _client: OwnedFd = client.into();
+///
+/// // When the compositor notices the client is gone
+/// registry.disconnect(id);
+/// assert!(registry.is_empty());
+/// # drop(server);
+/// ```
+pub struct ClientRegistry<T> {
+    clients: Mutex<HashMap<ClientId, T>>,
+    next_id: AtomicU64,
+    on_disconnect: Box<dyn Fn(ClientId, T) + Send + Sync>,
+}
+
+impl<T> ClientRegistry<T> {
+    /// Create an empty registry, calling `on_disconnect` with the state of every client which disconnects.
+    pub fn new(on_disconnect: impl Fn(ClientId, T) + Send + Sync + 'static) -> Self {
+        ClientRegistry {
+            clients: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            on_disconnect: Box::new(on_disconnect),
+        }
+    }
+
+    /// Register a new client with its state.
+    pub fn register(&self, state: T) -> ClientId {
+        let id = ClientId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.lock().insert(id, state);
+        id
+    }
+
+    /// Report that a client disconnected, calling the disconnect callback with its state.
+    ///
+    /// Returns `false` if the client is not registered, e.g. because it already disconnected.
+    pub fn disconnect(&self, id: ClientId) -> bool {
+        // Release the lock before calling out, so the callback can use the registry.
+        let state = self.lock().remove(&id);
+        match state {
+            Some(state) => {
+                (self.on_disconnect)(id, state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run `f` on the state of a connected client.
+    pub fn with_client<R>(&self, id: ClientId, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        self.lock().get_mut(&id).map(f)
+    }
+
+    /// Disconnect all clients, e.g. when the server stops.
+    pub fn disconnect_all(&self) {
+        let clients = std::mem::take(&mut *self.lock());
+        for (id, state) in clients {
+            (self.on_disconnect)(id, state);
+        }
+    }
+
+    /// Number of connected clients.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no client is connected.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<ClientId, T>> {
+        self.clients
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<T> fmt::Debug for ClientRegistry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientRegistry")
+            .field("clients", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        os::{fd::AsFd, unix::net::UnixStream},
+        sync::Arc,
+    };
+
+    use super::*;
+
+    #[test]
+    fn peer_cred_of_pair() {
+        let (server, _client) = UnixStream::pair().unwrap();
+        let cred = peer_cred(server.as_fd()).unwrap();
+        assert_eq!(cred.pid, std::process::id() as i32);
+    }
+
+    #[test]
+    fn socketpair_is_stream() {
+        use std::io::{Read, Write};
+
+        let (server, client) = create_socketpair().unwrap();
+        let (mut server, mut client) = (UnixStream::from(server), UnixStream::from(client));
+        client.write_all(b"wl_").unwrap();
+        client.write_all(b"display").unwrap();
+        let mut buf = [0; 10];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"wl_display");
+    }
+
+    #[test]
+    fn prune_closed_sockets() {
+        let (server, client) = UnixStream::pair().unwrap();
+        let mut tracked: Vec<_> = [server.as_fd(), client.as_fd()]
+            .into_iter()
+            .filter_map(TrackedSocket::new)
+            .collect();
+        assert_eq!(prune_closed(&mut tracked), 2);
+
+        drop(client);
+        assert_eq!(prune_closed(&mut tracked), 1);
+        drop(server);
+        assert_eq!(prune_closed(&mut tracked), 0);
+    }
+
+    #[test]
+    fn registry_disconnect() {
+        let disconnected = Arc::new(Mutex::new(Vec::new()));
+        let registry = ClientRegistry::new({
+            let disconnected = disconnected.clone();
+            move |_, state| disconnected.lock().unwrap().push(state)
+        });
+
+        let first = registry.register(1);
+        let second = registry.register(2);
+        assert_ne!(first, second);
+        assert_eq!(registry.len(), 2);
+
+        assert!(registry.disconnect(second));
+        assert!(!registry.disconnect(second));
+        assert_eq!(registry.with_client(first, |state| *state), Some(1));
+
+        registry.disconnect_all();
+        assert!(registry.is_empty());
+        assert_eq!(*disconnected.lock().unwrap(), [2, 1]);
+    }
+
+    #[test]
+    fn runtime_dir() {
+        let temp = std::env::temp_dir();
+        assert_eq!(
+            runtime_dir_from(Some(temp.clone().into())),
+            Ok(temp.clone())
+        );
+        assert!(runtime_dir_from(None).is_err());
+        assert!(runtime_dir_from(Some("relative".into())).is_err());
+        assert!(runtime_dir_from(Some(temp.join("wlcs-rs-missing").into())).is_err());
+
+        let name = format!("wlcs-rs-test-{}", std::process::id());
+        let listener = bind_listener_in(&temp, &name).unwrap();
+        assert!(bind_listener_in(&temp, &name).is_err());
+        assert!(bind_listener_in(&temp, "../wayland-0").is_err());
+        drop(listener);
+        std::fs::remove_file(temp.join(name)).unwrap();
+    }
+}
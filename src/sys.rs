@@ -0,0 +1,226 @@
+//!
+//! System calls used by the wrappers
+//!
+//! These go through `nix` by default, or directly through `libc` when the `nix` feature is disabled, to keep the
+//! dependency tree small. Calls without an equivalent among the enabled `nix` features always use `libc`.
+//!
+
+use std::{
+    io,
+    os::fd::{BorrowedFd, OwnedFd, RawFd},
+};
+
+use crate::{
+    ffi_wrappers::SigpipeMode,
+    socket::{PeerCred, SocketType},
+};
+
+/// Set the process-wide disposition of `SIGPIPE` according to `mode`.
+pub(crate) fn set_sigpipe_disposition(mode: SigpipeMode) -> io::Result<()> {
+    #[cfg(feature = "nix")]
+    {
+        use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+
+        let handler = match mode {
+            SigpipeMode::Ignore => SigHandler::SigIgn,
+            SigpipeMode::Default => SigHandler::SigDfl,
+            SigpipeMode::Leave => return Ok(()),
+        };
+        unsafe {
+            sigaction(
+                Signal::SIGPIPE,
+                &SigAction::new(handler, SaFlags::empty(), SigSet::empty()),
+            )?;
+        }
+        Ok(())
+    }
+    #[cfg(not(feature = "nix"))]
+    {
+        let handler = match mode {
+            SigpipeMode::Ignore => libc::SIG_IGN,
+            SigpipeMode::Default => libc::SIG_DFL,
+            SigpipeMode::Leave => return Ok(()),
+        };
+        // SAFETY: an all-zero sigaction is valid, and only the handler and an empty mask are set on top of it.
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(libc::SIGPIPE, &action, std::ptr::null_mut()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Block `SIGPIPE` on the calling thread.
+pub(crate) fn block_sigpipe() -> io::Result<()> {
+    #[cfg(feature = "nix")]
+    {
+        use nix::sys::signal::{SigSet, Signal};
+
+        let mut set = SigSet::empty();
+        set.add(Signal::SIGPIPE);
+        set.thread_block()?;
+        Ok(())
+    }
+    #[cfg(not(feature = "nix"))]
+    {
+        // SAFETY: the set is initialized by sigemptyset before use.
+        unsafe {
+            let mut set: libc::sigset_t = std::mem::zeroed();
+            libc::sigemptyset(&mut set);
+            libc::sigaddset(&mut set, libc::SIGPIPE);
+            match libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut()) {
+                0 => Ok(()),
+                errno => Err(io::Error::from_raw_os_error(errno)),
+            }
+        }
+    }
+}
+
+/// Get the `SO_PEERCRED` credentials of a Unix socket.
+pub(crate) fn peer_cred(fd: BorrowedFd<'_>) -> io::Result<PeerCred> {
+    #[cfg(feature = "nix")]
+    {
+        use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+
+        let cred = getsockopt(&fd, PeerCredentials)?;
+        Ok(PeerCred {
+            pid: cred.pid(),
+            uid: cred.uid(),
+            gid: cred.gid(),
+        })
+    }
+    #[cfg(not(feature = "nix"))]
+    {
+        use std::os::fd::AsRawFd;
+
+        let mut cred = libc::ucred {
+            pid: 0,
+            uid: 0,
+            gid: 0,
+        };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                fd.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut cred as *mut libc::ucred as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(PeerCred {
+            pid: cred.pid,
+            uid: cred.uid,
+            gid: cred.gid,
+        })
+    }
+}
+
+/// Create a connected, close-on-exec pair of Unix sockets of type `ty`.
+pub(crate) fn socketpair(ty: SocketType) -> io::Result<(OwnedFd, OwnedFd)> {
+    #[cfg(feature = "nix")]
+    {
+        use nix::sys::socket::{socketpair, AddressFamily, SockFlag, SockType};
+
+        let ty = match ty {
+            SocketType::Stream => SockType::Stream,
+            SocketType::SeqPacket => SockType::SeqPacket,
+        };
+        Ok(socketpair(
+            AddressFamily::Unix,
+            ty,
+            None,
+            SockFlag::SOCK_CLOEXEC,
+        )?)
+    }
+    #[cfg(not(feature = "nix"))]
+    {
+        use std::os::fd::FromRawFd;
+
+        let ty = match ty {
+            SocketType::Stream => libc::SOCK_STREAM,
+            SocketType::SeqPacket => libc::SOCK_SEQPACKET,
+        };
+        let mut fds = [-1; 2];
+        if unsafe { libc::socketpair(libc::AF_UNIX, ty | libc::SOCK_CLOEXEC, 0, fds.as_mut_ptr()) }
+            != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: socketpair succeeded, so both fds are open and owned by nothing else.
+        unsafe { Ok((OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))) }
+    }
+}
+
+/// Copy the memory of the current process at `addr` into `buf`, without dereferencing `addr`.
+///
+/// Fails with `EFAULT` if the memory is not readable, e.g. because `addr` is dangling, or if
+/// `process_vm_readv` is not permitted.
+pub(crate) fn read_memory(addr: usize, buf: &mut [u8]) -> io::Result<()> {
+    let local = libc::iovec {
+        iov_base: buf.as_mut_ptr().cast(),
+        iov_len: buf.len(),
+    };
+    let remote = libc::iovec {
+        iov_base: addr as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    let read = unsafe { libc::process_vm_readv(libc::getpid(), &local, 1, &remote, 1, 0) };
+    if read < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if read as usize != buf.len() {
+        return Err(io::Error::from_raw_os_error(libc::EFAULT));
+    }
+    Ok(())
+}
+
+/// Size of a memory page.
+pub(crate) fn page_size() -> usize {
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as usize,
+        _ => 4096,
+    }
+}
+
+/// Whether `fd` refers to an open file description.
+///
+/// An `OwnedFd` can only be closed behind our back through unsafe code, e.g. `from_raw_fd` on an fd which is
+/// also owned elsewhere, so this is meant for debug assertions.
+pub(crate) fn fd_is_open(fd: BorrowedFd<'_>) -> bool {
+    use std::os::fd::AsRawFd;
+
+    unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFD) != -1 }
+}
+
+/// The time of the monotonic clock in microseconds, the clock compositors stamp input events with.
+pub(crate) fn monotonic_usec() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: ts is a valid buffer, and CLOCK_MONOTONIC is always supported.
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000 + ts.tv_nsec as u64 / 1000
+}
+
+/// The device and inode of the file open as `fd`, or `None` if `fd` is not open.
+///
+/// `fd` is a raw fd as it may have been closed, in which case the number may also have been reused for another
+/// file, which has a different inode.
+pub(crate) fn fd_inode(fd: RawFd) -> Option<(u64, u64)> {
+    let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+    if unsafe { libc::fstat(fd, stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // SAFETY: fstat succeeded, so it initialized the buffer.
+    let stat = unsafe { stat.assume_init() };
+    Some((stat.st_dev, stat.st_ino))
+}
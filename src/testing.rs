@@ -0,0 +1,840 @@
+//!
+//! Helpers for testing WLCS integrations
+//!
+//! [`RecordingPointer`] and [`RecordingTouch`] wrap the devices of an integration, forwarding every call while
+//! recording it into a shared [`EventLog`], so tests can assert on the exact sequence of events WLCS sent.
+//!
+//! [`FnPointer`] and [`FnTouch`] build devices from closures, for throwaway integrations and tests.
+//!
+//! [`verify_symbols`] checks that a built integration library exports what WLCS looks for, and [`WlcsRun`] runs
+//! the WLCS test suite against it.
+//!
+
+use std::{
+    ffi::{CStr, CString},
+    fmt,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    ffi_display_server_api::{WlcsServerIntegration, WLCS_SERVER_INTEGRATION_VERSION},
+    fixed::Fixed,
+    geometry::{FixedPoint, Rectangle},
+    Pointer, Touch, WlcsError,
+};
+
+/// An input event recorded by [`RecordingPointer`] or [`RecordingTouch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordedEvent {
+    /// [`Pointer::move_absolute`]
+    PointerMoveAbsolute {
+        /// x coordinate
+        x: Fixed,
+        /// y coordinate
+        y: Fixed,
+    },
+    /// [`Pointer::move_relative`] or [`Pointer::move_relative_with_unaccel`]
+    PointerMoveRelative {
+        /// x delta
+        dx: Fixed,
+        /// y delta
+        dy: Fixed,
+        /// Unaccelerated x delta
+        dx_unaccel: Fixed,
+        /// Unaccelerated y delta
+        dy_unaccel: Fixed,
+    },
+    /// [`Pointer::relative_motion_timed`]
+    PointerRelativeMotionTimed {
+        /// Timestamp in microseconds
+        utime: u64,
+        /// x delta
+        dx: Fixed,
+        /// y delta
+        dy: Fixed,
+        /// Unaccelerated x delta
+        dx_unaccel: Fixed,
+        /// Unaccelerated y delta
+        dy_unaccel: Fixed,
+    },
+    /// [`Pointer::button_up`]
+    PointerButtonUp {
+        /// Button code
+        button: i32,
+    },
+    /// [`Pointer::button_down`]
+    PointerButtonDown {
+        /// Button code
+        button: i32,
+    },
+    /// [`Pointer::axis_source`]
+    PointerAxisSource {
+        /// Axis source
+        source: u32,
+    },
+    /// [`Pointer::axis_stop`]
+    PointerAxisStop {
+        /// Axis
+        axis: u32,
+    },
+    /// [`Pointer::set_time`]
+    PointerSetTime {
+        /// Timestamp in milliseconds
+        time_msec: u32,
+    },
+    /// [`Pointer::remove`]
+    PointerRemove,
+    /// [`Pointer::destroy`]
+    PointerDestroy,
+    /// [`Touch::touch_down`]
+    TouchDown {
+        /// x coordinate
+        x: Fixed,
+        /// y coordinate
+        y: Fixed,
+    },
+    /// [`Touch::touch_move`]
+    TouchMove {
+        /// x coordinate
+        x: Fixed,
+        /// y coordinate
+        y: Fixed,
+    },
+    /// [`Touch::touch_up`]
+    TouchUp,
+    /// [`Touch::frame`]
+    TouchFrame,
+    /// [`Touch::shape`]
+    TouchShape {
+        /// Touch point id
+        id: i32,
+        /// Length of the major axis
+        major: Fixed,
+        /// Length of the minor axis
+        minor: Fixed,
+    },
+    /// [`Touch::orientation`]
+    TouchOrientation {
+        /// Touch point id
+        id: i32,
+        /// Orientation in degrees
+        orientation: Fixed,
+    },
+    /// [`Touch::destroy`]
+    TouchDestroy,
+}
+
+/// A log of recorded events, shared between devices and the test inspecting it.
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    events: Arc<Mutex<Vec<RecordedEvent>>>,
+}
+
+impl EventLog {
+    /// Create an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A copy of the events recorded so far.
+    pub fn events(&self) -> Vec<RecordedEvent> {
+        self.lock().clone()
+    }
+
+    /// Take the events recorded so far, leaving the log empty.
+    pub fn take(&self) -> Vec<RecordedEvent> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn push(&self, event: RecordedEvent) {
+        self.lock().push(event);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<RecordedEvent>> {
+        // A panic while recording cannot leave the log inconsistent.
+        self.events
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A [`Pointer`] forwarding to `P` while recording every event.
+#[derive(Debug)]
+pub struct RecordingPointer<P> {
+    inner: P,
+    log: EventLog,
+}
+
+impl<P> RecordingPointer<P> {
+    /// Wrap `inner`, recording into `log`.
+    pub fn new(inner: P, log: EventLog) -> Self {
+        RecordingPointer { inner, log }
+    }
+
+    /// The wrapped pointer.
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// The wrapped pointer.
+    pub fn inner_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+
+    /// Unwrap the pointer.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: Pointer> Pointer for RecordingPointer<P> {
+    fn move_absolute(&mut self, x: Fixed, y: Fixed) {
+        self.log.push(RecordedEvent::PointerMoveAbsolute { x, y });
+        self.inner.move_absolute(x, y);
+    }
+
+    fn move_relative(&mut self, dx: Fixed, dy: Fixed) {
+        self.log.push(RecordedEvent::PointerMoveRelative {
+            dx,
+            dy,
+            dx_unaccel: dx,
+            dy_unaccel: dy,
+        });
+        self.inner.move_relative(dx, dy);
+    }
+
+    fn move_relative_with_unaccel(
+        &mut self,
+        dx: Fixed,
+        dy: Fixed,
+        dx_unaccel: Fixed,
+        dy_unaccel: Fixed,
+    ) {
+        self.log.push(RecordedEvent::PointerMoveRelative {
+            dx,
+            dy,
+            dx_unaccel,
+            dy_unaccel,
+        });
+        self.inner
+            .move_relative_with_unaccel(dx, dy, dx_unaccel, dy_unaccel);
+    }
+
+    fn relative_motion_timed(
+        &mut self,
+        utime: u64,
+        dx: Fixed,
+        dy: Fixed,
+        dx_unaccel: Fixed,
+        dy_unaccel: Fixed,
+    ) {
+        self.log.push(RecordedEvent::PointerRelativeMotionTimed {
+            utime,
+            dx,
+            dy,
+            dx_unaccel,
+            dy_unaccel,
+        });
+        self.inner
+            .relative_motion_timed(utime, dx, dy, dx_unaccel, dy_unaccel);
+    }
+
+    fn move_absolute_f64(&mut self, x: f64, y: f64) {
+        self.log.push(RecordedEvent::PointerMoveAbsolute {
+            x: Fixed::from_f64(x),
+            y: Fixed::from_f64(y),
+        });
+        self.inner.move_absolute_f64(x, y);
+    }
+
+    fn move_absolute_point(&mut self, point: FixedPoint) {
+        self.log.push(RecordedEvent::PointerMoveAbsolute {
+            x: point.x,
+            y: point.y,
+        });
+        self.inner.move_absolute_point(point);
+    }
+
+    fn button_up(&mut self, button: i32) {
+        self.log.push(RecordedEvent::PointerButtonUp { button });
+        self.inner.button_up(button);
+    }
+
+    fn button_down(&mut self, button: i32) {
+        self.log.push(RecordedEvent::PointerButtonDown { button });
+        self.inner.button_down(button);
+    }
+
+    fn last_serial(&self) -> Option<u32> {
+        self.inner.last_serial()
+    }
+
+    fn position(&self) -> Option<FixedPoint> {
+        self.inner.position()
+    }
+
+    fn set_bounds(&mut self, region: Rectangle) {
+        self.inner.set_bounds(region)
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        self.inner.bounds()
+    }
+
+    fn set_confine_region(&mut self, region: Option<Rectangle>) {
+        self.inner.set_confine_region(region)
+    }
+
+    fn confine_region(&self) -> Option<Rectangle> {
+        self.inner.confine_region()
+    }
+
+    fn axis_source(&mut self, source: u32) {
+        self.log.push(RecordedEvent::PointerAxisSource { source });
+        self.inner.axis_source(source);
+    }
+
+    fn axis_stop(&mut self, axis: u32) {
+        self.log.push(RecordedEvent::PointerAxisStop { axis });
+        self.inner.axis_stop(axis);
+    }
+
+    fn set_interface_version(&mut self, version: u32) {
+        self.inner.set_interface_version(version);
+    }
+
+    fn set_coalesce_interval(&mut self, interval: Duration) {
+        self.inner.set_coalesce_interval(interval);
+    }
+
+    fn set_time(&mut self, time_msec: u32) {
+        self.log.push(RecordedEvent::PointerSetTime { time_msec });
+        self.inner.set_time(time_msec);
+    }
+
+    fn remove(&mut self) {
+        self.log.push(RecordedEvent::PointerRemove);
+        self.inner.remove();
+    }
+
+    fn destroy(&mut self) {
+        self.log.push(RecordedEvent::PointerDestroy);
+        self.inner.destroy();
+    }
+}
+
+/// A [`Touch`] forwarding to `T` while recording every event.
+#[derive(Debug)]
+pub struct RecordingTouch<T> {
+    inner: T,
+    log: EventLog,
+}
+
+impl<T> RecordingTouch<T> {
+    /// Wrap `inner`, recording into `log`.
+    pub fn new(inner: T, log: EventLog) -> Self {
+        RecordingTouch { inner, log }
+    }
+
+    /// The wrapped touch.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// The wrapped touch.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the touch.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: Touch> Touch for RecordingTouch<T> {
+    fn touch_down(&mut self, x: Fixed, y: Fixed) {
+        self.log.push(RecordedEvent::TouchDown { x, y });
+        self.inner.touch_down(x, y);
+    }
+
+    fn touch_down_f64(&mut self, x: f64, y: f64) {
+        self.log.push(RecordedEvent::TouchDown {
+            x: Fixed::from_f64(x),
+            y: Fixed::from_f64(y),
+        });
+        self.inner.touch_down_f64(x, y);
+    }
+
+    fn touch_move(&mut self, x: Fixed, y: Fixed) {
+        self.log.push(RecordedEvent::TouchMove { x, y });
+        self.inner.touch_move(x, y);
+    }
+
+    fn touch_down_point(&mut self, point: FixedPoint) {
+        self.log.push(RecordedEvent::TouchDown {
+            x: point.x,
+            y: point.y,
+        });
+        self.inner.touch_down_point(point);
+    }
+
+    fn touch_move_point(&mut self, point: FixedPoint) {
+        self.log.push(RecordedEvent::TouchMove {
+            x: point.x,
+            y: point.y,
+        });
+        self.inner.touch_move_point(point);
+    }
+
+    fn touch_up(&mut self) {
+        self.log.push(RecordedEvent::TouchUp);
+        self.inner.touch_up();
+    }
+
+    fn set_interface_version(&mut self, version: u32) {
+        self.inner.set_interface_version(version);
+    }
+
+    fn frame(&mut self) {
+        self.log.push(RecordedEvent::TouchFrame);
+        self.inner.frame();
+    }
+
+    fn shape(&mut self, id: i32, major: Fixed, minor: Fixed) {
+        self.log
+            .push(RecordedEvent::TouchShape { id, major, minor });
+        self.inner.shape(id, major, minor);
+    }
+
+    fn orientation(&mut self, id: i32, orientation: Fixed) {
+        self.log
+            .push(RecordedEvent::TouchOrientation { id, orientation });
+        self.inner.orientation(id, orientation);
+    }
+
+    fn output_geometry(&self) -> Option<Rectangle> {
+        self.inner.output_geometry()
+    }
+
+    fn destroy(&mut self) {
+        self.log.push(RecordedEvent::TouchDestroy);
+        self.inner.destroy();
+    }
+}
+
+type FnMove = Box<dyn FnMut(Fixed, Fixed)>;
+type FnButton = Box<dyn FnMut(i32)>;
+type FnEvent = Box<dyn FnMut()>;
+
+/// A [`Pointer`] calling a closure for each event, built with the `on_*` methods.
+///
+/// Events without a closure are ignored. The closures are `'static`, like the devices returned by
+/// [`Wlcs::create_pointer`](crate::Wlcs::create_pointer), so state shared with the test goes through an `Rc` or
+/// an [`EventLog`].
+///
+/// # Examples
+///
+/// ```
+/// use std::{cell::Cell, rc::Rc};
+/// use wlcs::{fixed::Fixed, testing::FnPointer, Pointer};
+///
+/// let clicks = Rc::new(Cell::new(0));
+/// let mut pointer = FnPointer::new().on_button_down({
+///     let clicks = clicks.clone();
+///     move |_button| clicks.set(clicks.get() + 1)
+/// });
+///
+/// pointer.move_absolute(Fixed::from_int(1), Fixed::from_int(1));
+/// pointer.button_down(0x110);
+/// assert_eq!(clicks.get(), 1);
+/// ```
+#[derive(Default)]
+pub struct FnPointer {
+    move_absolute: Option<FnMove>,
+    move_relative: Option<FnMove>,
+    button_up: Option<FnButton>,
+    button_down: Option<FnButton>,
+    destroy: Option<FnEvent>,
+}
+
+impl FnPointer {
+    /// A pointer ignoring every event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `f` on [`Pointer::move_absolute`].
+    pub fn on_move_absolute(mut self, f: impl FnMut(Fixed, Fixed) + 'static) -> Self {
+        self.move_absolute = Some(Box::new(f));
+        self
+    }
+
+    /// Call `f` on [`Pointer::move_relative`].
+    pub fn on_move_relative(mut self, f: impl FnMut(Fixed, Fixed) + 'static) -> Self {
+        self.move_relative = Some(Box::new(f));
+        self
+    }
+
+    /// Call `f` on [`Pointer::button_up`].
+    pub fn on_button_up(mut self, f: impl FnMut(i32) + 'static) -> Self {
+        self.button_up = Some(Box::new(f));
+        self
+    }
+
+    /// Call `f` on [`Pointer::button_down`].
+    pub fn on_button_down(mut self, f: impl FnMut(i32) + 'static) -> Self {
+        self.button_down = Some(Box::new(f));
+        self
+    }
+
+    /// Call `f` on [`Pointer::destroy`].
+    pub fn on_destroy(mut self, f: impl FnMut() + 'static) -> Self {
+        self.destroy = Some(Box::new(f));
+        self
+    }
+}
+
+impl Pointer for FnPointer {
+    fn move_absolute(&mut self, x: Fixed, y: Fixed) {
+        if let Some(f) = &mut self.move_absolute {
+            f(x, y);
+        }
+    }
+
+    fn move_relative(&mut self, dx: Fixed, dy: Fixed) {
+        if let Some(f) = &mut self.move_relative {
+            f(dx, dy);
+        }
+    }
+
+    fn button_up(&mut self, button: i32) {
+        if let Some(f) = &mut self.button_up {
+            f(button);
+        }
+    }
+
+    fn button_down(&mut self, button: i32) {
+        if let Some(f) = &mut self.button_down {
+            f(button);
+        }
+    }
+
+    fn destroy(&mut self) {
+        if let Some(f) = &mut self.destroy {
+            f();
+        }
+    }
+}
+
+impl fmt::Debug for FnPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnPointer").finish_non_exhaustive()
+    }
+}
+
+/// A [`Touch`] calling a closure for each event, see [`FnPointer`].
+#[derive(Default)]
+pub struct FnTouch {
+    touch_down: Option<FnMove>,
+    touch_move: Option<FnMove>,
+    touch_up: Option<FnEvent>,
+    destroy: Option<FnEvent>,
+}
+
+impl FnTouch {
+    /// A touch device ignoring every event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `f` on [`Touch::touch_down`].
+    pub fn on_touch_down(mut self, f: impl FnMut(Fixed, Fixed) + 'static) -> Self {
+        self.touch_down = Some(Box::new(f));
+        self
+    }
+
+    /// Call `f` on [`Touch::touch_move`].
+    pub fn on_touch_move(mut self, f: impl FnMut(Fixed, Fixed) + 'static) -> Self {
+        self.touch_move = Some(Box::new(f));
+        self
+    }
+
+    /// Call `f` on [`Touch::touch_up`].
+    pub fn on_touch_up(mut self, f: impl FnMut() + 'static) -> Self {
+        self.touch_up = Some(Box::new(f));
+        self
+    }
+
+    /// Call `f` on [`Touch::destroy`].
+    pub fn on_destroy(mut self, f: impl FnMut() + 'static) -> Self {
+        self.destroy = Some(Box::new(f));
+        self
+    }
+}
+
+impl Touch for FnTouch {
+    fn touch_down(&mut self, x: Fixed, y: Fixed) {
+        if let Some(f) = &mut self.touch_down {
+            f(x, y);
+        }
+    }
+
+    fn touch_move(&mut self, x: Fixed, y: Fixed) {
+        if let Some(f) = &mut self.touch_move {
+            f(x, y);
+        }
+    }
+
+    fn touch_up(&mut self) {
+        if let Some(f) = &mut self.touch_up {
+            f();
+        }
+    }
+
+    fn destroy(&mut self) {
+        if let Some(f) = &mut self.destroy {
+            f();
+        }
+    }
+}
+
+impl fmt::Debug for FnTouch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnTouch").finish_non_exhaustive()
+    }
+}
+
+/// Check that the integration library at `path` exports a usable `wlcs_server_integration` symbol.
+///
+/// The library is loaded with `dlopen` like WLCS does, then the symbol is looked up and checked to be a
+/// [`WlcsServerIntegration`] of the version this crate builds, with all entry points set. This catches a missing
+/// [`wlcs_server_integration!`](crate::wlcs_server_integration) invocation or a non-`cdylib` crate type before a
+/// full WLCS run.
+///
+/// Loading the library runs its initializers, and it is unloaded again before returning.
+pub fn verify_symbols(path: impl AsRef<Path>) -> Result<(), WlcsError> {
+    let path = CString::new(path.as_ref().as_os_str().as_bytes())
+        .map_err(|_| "library path contains a nul byte")?;
+
+    let library = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if library.is_null() {
+        return Err(dl_error("dlopen").into());
+    }
+
+    let symbol = unsafe { libc::dlsym(library, b"wlcs_server_integration\0".as_ptr().cast()) };
+    let result = if symbol.is_null() {
+        Err(dl_error("dlsym").into())
+    } else {
+        // SAFETY: WLCS interprets the symbol the same way, and the version is the first field of every revision.
+        let integration = unsafe { &*(symbol as *const WlcsServerIntegration) };
+        if integration.version != WLCS_SERVER_INTEGRATION_VERSION {
+            Err(format!(
+                "wlcs_server_integration has version {}, expected {}",
+                integration.version, WLCS_SERVER_INTEGRATION_VERSION
+            )
+            .into())
+        } else if integration.create_server.is_none() || integration.destroy_server.is_none() {
+            Err("wlcs_server_integration is missing entry points".into())
+        } else {
+            Ok(())
+        }
+    };
+
+    unsafe { libc::dlclose(library) };
+    result
+}
+
+/// Outcome of a WLCS test, see [`WlcsRun`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TestOutcome {
+    /// The test passed
+    Passed,
+    /// The test failed
+    Failed,
+    /// The test was skipped, e.g. because the integration does not advertise an extension it needs
+    Skipped,
+}
+
+/// Result of a WLCS test, see [`WlcsRun`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    /// Full gtest name of the test, e.g. `SelfTest.successful_test_passes`
+    pub name: String,
+    /// Outcome of the test
+    pub outcome: TestOutcome,
+}
+
+/// A run of the WLCS test suite against an integration library.
+///
+/// The `wlcs` binary is run with the library and, if set, a gtest filter selecting the tests, so `cargo test`
+/// can run a subset relevant to the feature under development. The results are parsed from the output of
+/// gtest; tests which crash WLCS have no result.
+///
+/// # Examples
+///
+/// ```no_run
+/// use wlcs::testing::{TestOutcome, WlcsRun};
+///
+/// let results = WlcsRun::new("/usr/libexec/wlcs/wlcs", "target/debug/libmy_integration.so")
+///     .filter("ClientSurfaceEvents*")
+///     .run()
+///     .unwrap();
+/// assert!(results.iter().all(|result| result.outcome != TestOutcome::Failed));
+/// ```
+#[derive(Debug, Clone)]
+pub struct WlcsRun {
+    binary: PathBuf,
+    library: PathBuf,
+    filter: Option<String>,
+}
+
+impl WlcsRun {
+    /// Run the `wlcs` binary at `binary` against the integration library at `library`.
+    pub fn new(binary: impl Into<PathBuf>, library: impl Into<PathBuf>) -> Self {
+        WlcsRun {
+            binary: binary.into(),
+            library: library.into(),
+            filter: None,
+        }
+    }
+
+    /// Only run the tests matching the gtest `filter`, e.g. `BadBufferTest.*:-*test_truncated*`.
+    pub fn filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    /// Run the tests, returning the result of each test run.
+    ///
+    /// Failing tests do not make this fail, only failing to run WLCS does.
+    pub fn run(&self) -> Result<Vec<TestResult>, WlcsError> {
+        let mut command = Command::new(&self.binary);
+        command.arg(&self.library);
+        if let Some(filter) = &self.filter {
+            command.arg(format!("--gtest_filter={}", filter));
+        }
+        let output = command.output()?;
+        Ok(parse_gtest_output(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Parse the results of a gtest run from its output.
+///
+/// A result is only taken from the line ending a test started by a `RUN` line, as the summary at the end of the
+/// run lists the failed tests again.
+fn parse_gtest_output(output: &str) -> Vec<TestResult> {
+    let mut results = Vec::new();
+    let mut running: Option<&str> = None;
+    for line in output.lines() {
+        let Some((status, rest)) = line.strip_prefix('[').and_then(|line| line.split_once(']'))
+        else {
+            continue;
+        };
+        let name = rest.trim().split(' ').next().unwrap_or_default();
+        let outcome = match status.trim() {
+            "RUN" => {
+                running = Some(name);
+                continue;
+            }
+            "OK" => TestOutcome::Passed,
+            "FAILED" => TestOutcome::Failed,
+            "SKIPPED" => TestOutcome::Skipped,
+            _ => continue,
+        };
+        if running == Some(name) {
+            running = None;
+            results.push(TestResult {
+                name: name.to_owned(),
+                outcome,
+            });
+        }
+    }
+    results
+}
+
+fn dl_error(function: &str) -> String {
+    let message = unsafe { libc::dlerror() };
+    if message.is_null() {
+        format!("{} failed", function)
+    } else {
+        format!("{} failed: {}", function, unsafe {
+            CStr::from_ptr(message).to_string_lossy()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::{NoopPointer, NoopTouch};
+
+    #[test]
+    fn records_in_order() {
+        let log = EventLog::new();
+        let mut pointer = RecordingPointer::new(NoopPointer, log.clone());
+        let mut touch = RecordingTouch::new(NoopTouch, log.clone());
+
+        pointer.move_absolute(Fixed::from_int(1), Fixed::from_int(2));
+        pointer.button_down(0x110);
+        touch.touch_down(Fixed::ZERO, Fixed::ZERO);
+        pointer.button_up(0x110);
+        touch.touch_up();
+
+        assert_eq!(
+            log.take(),
+            [
+                RecordedEvent::PointerMoveAbsolute {
+                    x: Fixed(256),
+                    y: Fixed(512)
+                },
+                RecordedEvent::PointerButtonDown { button: 0x110 },
+                RecordedEvent::TouchDown {
+                    x: Fixed::ZERO,
+                    y: Fixed::ZERO
+                },
+                RecordedEvent::PointerButtonUp { button: 0x110 },
+                RecordedEvent::TouchUp,
+            ]
+        );
+        assert!(log.events().is_empty());
+    }
+
+    #[test]
+    fn parse_gtest() {
+        let output = "\
+[==========] Running 3 tests from 1 test suite.
+[ RUN      ] SelfTest.passes
+[       OK ] SelfTest.passes (1 ms)
+[ RUN      ] SelfTest.fails
+test.cpp:12: Failure
+[  FAILED  ] SelfTest.fails (2 ms)
+[ RUN      ] SelfTest.skipped
+[  SKIPPED ] SelfTest.skipped (0 ms)
+[==========] 3 tests from 1 test suite ran. (3 ms total)
+[  FAILED  ] 1 test, listed below:
+[  FAILED  ] SelfTest.fails
+";
+        let result = |name: &str, outcome| TestResult {
+            name: name.to_owned(),
+            outcome,
+        };
+        assert_eq!(
+            parse_gtest_output(output),
+            [
+                result("SelfTest.passes", TestOutcome::Passed),
+                result("SelfTest.fails", TestOutcome::Failed),
+                result("SelfTest.skipped", TestOutcome::Skipped),
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_symbols_missing_library() {
+        assert!(verify_symbols("/nonexistent/libwlcs_integration.so").is_err());
+    }
+}